@@ -0,0 +1,301 @@
+use xpln::{Xpln, Station, StopKind, Timetable, TrackKind, Train, XplnTime};
+
+/// What to write as previous station of a train's first stop and as next
+/// station of its last stop.
+#[derive(Clone, Debug)]
+pub enum Endpoints {
+    /// Leave the column empty.
+    Blank,
+    /// Write the name of the fiddle yard the train comes from or goes to.
+    FiddleYard(String),
+    /// Write `Beginn` for the first and `Ende` for the last stop.
+    Markers
+}
+
+/// How to write the train column.
+#[derive(Clone, Debug)]
+pub enum TrainNames {
+    /// Category and train number, e.g. `Dg 61`.
+    Short,
+    /// Designation as written in the traindef, e.g. `Dg 61 71`.
+    Original
+}
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub endpoints: Endpoints,
+    pub train_names: TrainNames,
+    /// Written as arrival of trains passing without a stop, followed by the
+    /// passing time as departure.
+    pub pass_marker: String,
+    /// Stations handled as fiddle yards in addition to those with fiddle
+    /// yard tracks.
+    pub fiddle_yards: Vec<String>
+}
+
+impl Options {
+    pub fn new() -> Options {
+        Options {
+            endpoints: Endpoints::Blank,
+            train_names: TrainNames::Short,
+            pass_marker: String::from("|"),
+            fiddle_yards: Vec::new()
+        }
+    }
+
+    fn is_fiddle_yard(&self, station: &Station, track: &str) -> bool {
+        self.fiddle_yards.contains(&station.name) ||
+            station.track(track).map(|t| t.kind == TrackKind::FiddleYard).unwrap_or(false)
+    }
+
+    fn train_name(&self, train: &Train) -> String {
+        match self.train_names {
+            TrainNames::Short => train.name(),
+            TrainNames::Original => train.original_name()
+        }
+    }
+
+    fn origin(&self) -> &str {
+        match self.endpoints {
+            Endpoints::Blank => "",
+            Endpoints::FiddleYard(ref name) => name,
+            Endpoints::Markers => "Beginn"
+        }
+    }
+
+    fn terminus(&self) -> &str {
+        match self.endpoints {
+            Endpoints::Blank => "",
+            Endpoints::FiddleYard(ref name) => name,
+            Endpoints::Markers => "Ende"
+        }
+    }
+}
+
+/// One line of a BFO document.
+struct Entry {
+    time: Option<XplnTime>,
+    arrival: String,
+    departure: String,
+    train: String,
+    previous: String,
+    next: String,
+    track: String,
+    remark: String
+}
+
+impl Entry {
+    fn new(xpln: &Xpln, train: &Train, index: usize, options: &Options) -> Entry {
+        let timetable = &train.timetables[index];
+
+        let (arrival, departure) = match timetable.kind {
+            StopKind::Pass => (options.pass_marker.clone(), XplnTime::format_hm(timetable.departure.or(timetable.arrival), "")),
+            _ => (XplnTime::format_hm(timetable.arrival, ""), XplnTime::format_hm(timetable.departure, ""))
+        };
+
+        let previous : &str = train.previous(index).map(|t: &Timetable| &t.station[..]).unwrap_or(options.origin());
+        let next     : &str = train.next(index).map(|t: &Timetable| &t.station[..]).unwrap_or(options.terminus());
+
+        Entry {
+            time: timetable.time(),
+            arrival: arrival,
+            departure: departure,
+            train: options.train_name(&xpln.trains[&timetable.train]),
+            previous: String::from(previous),
+            next: String::from(next),
+            track: timetable.track.clone(),
+            remark: timetable.remark.clone()
+        }
+    }
+
+    /// Joins a train ending on a fiddle yard track with the train later
+    /// leaving from it into one turnaround line.
+    fn turnaround(arriving: &Entry, departing: &Entry) -> Entry {
+        let remark = match (arriving.remark.is_empty(), departing.remark.is_empty()) {
+            (false, false) => format!("{} / {}", arriving.remark, departing.remark),
+            (false, true) => arriving.remark.clone(),
+            _ => departing.remark.clone()
+        };
+
+        Entry {
+            time: arriving.time,
+            arrival: arriving.arrival.clone(),
+            departure: departing.departure.clone(),
+            train: format!("{} / {}", arriving.train, departing.train),
+            previous: arriving.previous.clone(),
+            next: departing.next.clone(),
+            track: arriving.track.clone(),
+            remark: remark
+        }
+    }
+}
+
+/// Renders the BFO text document for `station`, one line per train calling
+/// at the station ordered by time.
+///
+/// In fiddle yards, i.e. on fiddle yard tracks or at stations listed in
+/// `Options::fiddle_yards`, trains ending their run only get an arrival and
+/// trains starting their run only get a departure. A train starting from the
+/// track another train ended on before is written as one turnaround line
+/// together with the latest such train.
+///
+/// Trains are visited in order of their number and all sorts are stable, so
+/// lines with equal times always come out in the same order.
+pub fn render(xpln: &Xpln, station: &Station, options: &Options) -> String {
+    let mut entries    = Vec::new();
+    let mut arrivals   = Vec::new();
+    let mut departures = Vec::new();
+
+    for train in xpln.sorted_trains() {
+        for (index, timetable) in train.timetables.iter().enumerate() {
+            if timetable.station != station.name { continue }
+
+            let mut entry = Entry::new(xpln, train, index, options);
+
+            if !options.is_fiddle_yard(station, &timetable.track) {
+                entries.push(entry);
+                continue;
+            }
+
+            match timetable.kind {
+                StopKind::Terminus => {
+                    entry.time      = timetable.arrival.or(timetable.departure);
+                    entry.arrival   = XplnTime::format_hm(entry.time, "");
+                    entry.departure = String::new();
+                    arrivals.push(entry);
+                },
+                StopKind::Origin => {
+                    entry.time      = timetable.departure.or(timetable.arrival);
+                    entry.arrival   = String::new();
+                    entry.departure = XplnTime::format_hm(entry.time, "");
+                    departures.push(entry);
+                },
+                _ => entries.push(entry)
+            }
+        }
+    }
+
+    arrivals.sort_by(|e0, e1| e0.time.cmp(&e1.time));
+    departures.sort_by(|e0, e1| e0.time.cmp(&e1.time));
+
+    let mut paired = vec![false; arrivals.len()];
+
+    for departing in departures.into_iter() {
+        let arriving = arrivals.iter().enumerate().rev().find(|&(index, arriving)| {
+            !paired[index] && !arriving.track.is_empty() && arriving.track == departing.track
+                && arriving.time <= departing.time
+        }).map(|(index, _)| index);
+
+        match arriving {
+            Some(index) => {
+                paired[index] = true;
+                entries.push(Entry::turnaround(&arrivals[index], &departing));
+            },
+            None => entries.push(departing)
+        }
+    }
+
+    for (entry, paired) in arrivals.into_iter().zip(paired.into_iter()) {
+        if !paired { entries.push(entry) }
+    }
+
+    entries.sort_by(|e0, e1| e0.time.cmp(&e1.time));
+
+    let mut data = String::new();
+
+    for entry in entries.iter() {
+        let line = format!("{arrival}\t{departure}\t{train}\t{previous}\t{next}\t{track}\t\t\t\t\t{remark}\n",
+            arrival=entry.arrival, departure=entry.departure, train=entry.train,
+            previous=entry.previous, next=entry.next, track=entry.track, remark=entry.remark
+        );
+
+        data.push_str(&line);
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use xpln::{Station, Timetable, Track, TrackKind, Train, TrainName, Xpln, XplnTime};
+    use super::{render, Options};
+
+    /// Builds a train calling at `stops`, each as station, track, arrival
+    /// and departure.
+    fn train(number: usize, stops: &[(&str, &str, &str, &str)]) -> Train {
+        let time = |time: &str| if time.is_empty() { None } else { Some(XplnTime::from_str(time).unwrap()) };
+
+        let mut train = Train::new(number, TrainName::parse(&format!("RB {}", number), "", ""), "");
+
+        for &(station, track, arrival, departure) in stops.iter() {
+            train.timetables.push(Timetable::new(number, String::from(track), String::from(station),
+                time(arrival), time(departure), String::new()));
+        }
+
+        train.update_stop_kinds();
+        train
+    }
+
+    /// Layout with the station `A` and the fiddle yard `S`.
+    fn xpln(trains: Vec<Train>) -> Xpln {
+        let mut xpln = Xpln::new();
+
+        let mut station = Station::new("A", "");
+        station.add_track(Track::new("A", "1", TrackKind::Platform, ""));
+        xpln.add_station(station);
+
+        let mut station = Station::new("S", "");
+        station.add_track(Track::new("S", "1", TrackKind::FiddleYard, ""));
+        station.add_track(Track::new("S", "2", TrackKind::FiddleYard, ""));
+        xpln.add_station(station);
+
+        for train in trains.into_iter() {
+            xpln.add_train(train);
+        }
+
+        xpln
+    }
+
+    #[test]
+    fn fiddle_yard_arrival_only() {
+        let xpln = xpln(vec![train(1, &[("A", "1", "", "6:00"), ("S", "1", "6:10", "6:10")])]);
+
+        assert_eq!("06:10\t\tRB 1\tA\t\t1\t\t\t\t\t\n", render(&xpln, xpln.station("S").unwrap(), &Options::new()));
+    }
+
+    #[test]
+    fn fiddle_yard_departure_only() {
+        let xpln = xpln(vec![train(1, &[("S", "1", "6:00", "6:00"), ("A", "1", "6:10", "")])]);
+
+        assert_eq!("\t06:00\tRB 1\t\tA\t1\t\t\t\t\t\n", render(&xpln, xpln.station("S").unwrap(), &Options::new()));
+    }
+
+    #[test]
+    fn fiddle_yard_turnaround_pairs_latest_arrival_on_same_track() {
+        let xpln = xpln(vec![
+            train(1, &[("A", "1", "", "6:00"), ("S", "1", "6:10", "")]),
+            train(2, &[("S", "1", "", "7:00"), ("A", "1", "7:10", "")]),
+            train(3, &[("A", "1", "", "6:20"), ("S", "1", "6:30", "")]),
+            train(4, &[("S", "2", "", "7:30"), ("A", "1", "7:40", "")])
+        ]);
+
+        assert_eq!(concat!(
+            "06:10\t\tRB 1\tA\t\t1\t\t\t\t\t\n",
+            "06:30\t07:00\tRB 3 / RB 2\tA\tA\t1\t\t\t\t\t\n",
+            "\t07:30\tRB 4\t\tA\t2\t\t\t\t\t\n"
+        ), render(&xpln, xpln.station("S").unwrap(), &Options::new()));
+    }
+
+    #[test]
+    fn equal_times_in_train_order() {
+        let trains = (1..7).map(|number| train(number, &[("A", "1", "", "6:00"), ("S", "1", "6:10", "")])).collect();
+        let xpln   = xpln(trains);
+        let data   = render(&xpln, xpln.station("A").unwrap(), &Options::new());
+
+        let names : Vec<&str> = data.lines().map(|line| line.split('\t').nth(2).unwrap()).collect();
+
+        assert_eq!(vec!["RB 1", "RB 2", "RB 3", "RB 4", "RB 5", "RB 6"], names);
+    }
+}
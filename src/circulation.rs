@@ -0,0 +1,164 @@
+use std::collections::HashSet;
+
+use xpln::{Xpln, Train, XplnTime};
+
+/// Link between a train and the train continuing with its stock.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Link {
+    pub from: usize,
+    pub to: usize,
+    /// Station where the stock changes from one train to the other.
+    pub station: String,
+    /// Link was inferred from the timetable instead of given in a remark.
+    pub inferred: bool
+}
+
+/// Trains run by one stock set over the session, in order.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Circulation {
+    pub trains: Vec<usize>,
+    /// Links between consecutive trains, one less than `trains`.
+    pub links: Vec<Link>
+}
+
+/// Returns the successor links of all trains ordered by train number.
+///
+/// Explicit links (`=> <train>`) are used as given. Every other train ending
+/// at a station is linked to the next train starting there after its arrival
+/// that has no predecessor yet. Of trains starting at the same time, the one
+/// starting from the same track is preferred. Trains are considered in order
+/// of their arrival.
+pub fn links(xpln: &Xpln) -> Vec<Link> {
+    let mut links = Vec::new();
+    let mut taken = HashSet::new();
+
+    for train in xpln.sorted_trains() {
+        if let Some(successor) = train.successor {
+            if let (Some(last), Some(_)) = (train.timetables.last(), xpln.train(successor)) {
+                links.push(Link { from: train.number, to: successor, station: last.station.clone(), inferred: false });
+                taken.insert(successor);
+            }
+        }
+    }
+
+    let mut ending : Vec<&Train> = xpln.trains.values()
+        .filter(|train| train.successor.is_none() && !train.timetables.is_empty())
+        .collect();
+
+    ending.sort_by(|t0, t1| (end(t0), t0.number).cmp(&(end(t1), t1.number)));
+
+    for train in ending {
+        let last = train.timetables.last().unwrap();
+
+        let mut candidates : Vec<&Train> = xpln.trains.values().filter(|other| {
+            match (other.timetables.first(), end(train), start(other)) {
+                (Some(first), Some(arrival), Some(departure)) => {
+                    other.number != train.number && !taken.contains(&other.number)
+                        && first.station == last.station && departure >= arrival
+                },
+                _ => false
+            }
+        }).collect();
+
+        candidates.sort_by(|t0, t1| {
+            let other_track0 = t0.timetables[0].track != last.track;
+            let other_track1 = t1.timetables[0].track != last.track;
+
+            (start(t0), other_track0, t0.number).cmp(&(start(t1), other_track1, t1.number))
+        });
+
+        if let Some(successor) = candidates.first() {
+            links.push(Link { from: train.number, to: successor.number, station: last.station.clone(), inferred: true });
+            taken.insert(successor.number);
+        }
+    }
+
+    links.sort_by(|l0, l1| l0.from.cmp(&l1.from));
+    links
+}
+
+/// Chains the successor links into circulations, one per stock set, ordered
+/// by their first train's start.
+pub fn circulations(xpln: &Xpln) -> Vec<Circulation> {
+    let links = links(xpln);
+
+    let mut trains : Vec<&Train> = xpln.trains.values().collect();
+    trains.sort_by(|t0, t1| (start(t0), t0.number).cmp(&(start(t1), t1.number)));
+
+    let successors : HashSet<usize> = links.iter().map(|link| link.to).collect();
+
+    let mut visited      = HashSet::new();
+    let mut circulations = Vec::new();
+
+    // Trains nobody continues into start a circulation; the remaining
+    // unvisited trains form cycles and start one at their earliest train.
+    let heads : Vec<usize> = trains.iter().filter(|t| !successors.contains(&t.number)).map(|t| t.number)
+        .chain(trains.iter().map(|t| t.number))
+        .collect();
+
+    for head in heads {
+        if visited.contains(&head) { continue }
+
+        let mut circulation = Circulation { trains: vec![head], links: Vec::new() };
+        let mut current     = head;
+
+        visited.insert(head);
+
+        while let Some(link) = links.iter().find(|link| link.from == current) {
+            if visited.contains(&link.to) { break }
+
+            visited.insert(link.to);
+            circulation.trains.push(link.to);
+            circulation.links.push(link.clone());
+            current = link.to;
+        }
+
+        circulations.push(circulation);
+    }
+
+    circulations
+}
+
+/// Renders the circulation report: one block per stock set listing its
+/// trains with first and last station and time.
+pub fn render(xpln: &Xpln, circulations: &[Circulation]) -> String {
+    let mut data = String::new();
+
+    for (index, circulation) in circulations.iter().enumerate() {
+        if index > 0 { data.push_str("\n") }
+
+        data.push_str(&format!("Circulation {} ({} train(s))\n", index + 1, circulation.trains.len()));
+
+        for (position, number) in circulation.trains.iter().enumerate() {
+            let train = &xpln.trains[number];
+
+            let (first, last) = match (train.timetables.first(), train.timetables.last()) {
+                (Some(first), Some(last)) => (&first.station[..], &last.station[..]),
+                _ => ("", "")
+            };
+
+            let last = if position > 0 && circulation.links[position - 1].inferred {
+                format!("{:<6} (inferred)", last)
+            } else {
+                String::from(last)
+            };
+
+            data.push_str(&format!("  {:<10} {} {:<6} - {} {}\n",
+                train.name(), XplnTime::format_hm(start(train), "--:--"), first, XplnTime::format_hm(end(train), "--:--"), last
+            ));
+        }
+    }
+
+    data
+}
+
+/// Departure from the train's first station.
+fn start(train: &Train) -> Option<XplnTime> {
+    train.timetables.first().and_then(|t| t.departure.or(t.arrival))
+}
+
+/// Arrival at the train's last station.
+fn end(train: &Train) -> Option<XplnTime> {
+    train.timetables.last().and_then(|t| t.arrival.or(t.departure))
+}
+
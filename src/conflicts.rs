@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter, Error};
+
+use xpln::{Xpln, Train, Timetable, XplnTime};
+
+/// Two trains occupying the same track of a station at overlapping times or
+/// closer to each other than the required clearance.
+#[derive(Clone, Debug)]
+pub struct Conflict {
+    pub station: String,
+    pub track: String,
+    pub first: Occupation,
+    pub second: Occupation,
+    /// Start of the overlap, i.e. the later of both arrivals.
+    pub from: XplnTime,
+    /// End of the overlap, i.e. the earlier of both departures. Before `from`
+    /// if the trains do not overlap but violate the clearance.
+    pub until: XplnTime
+}
+
+/// Time a train occupies a track.
+#[derive(Clone, Debug)]
+pub struct Occupation {
+    pub train: usize,
+    pub name: String,
+    pub row: usize,
+    pub start: XplnTime,
+    pub end: XplnTime
+}
+
+impl Occupation {
+    fn new(train: &Train, timetable: &Timetable) -> Option<Occupation> {
+        let start = match timetable.time() {
+            Some(start) => start,
+            None => return None
+        };
+
+        Some(Occupation {
+            train: train.number,
+            name: train.name(),
+            row: timetable.row,
+            start: start,
+            end: timetable.departure.unwrap_or(start)
+        })
+    }
+}
+
+impl Conflict {
+    pub fn overlaps(&self) -> bool {
+        self.until >= self.from
+    }
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        try!(write!(f, "{} track {}: {} ({}-{}) and {} ({}-{})",
+            self.station, self.track,
+            self.first.name, self.first.start, self.first.end,
+            self.second.name, self.second.start, self.second.end
+        ));
+
+        if self.overlaps() {
+            write!(f, " overlap from {} to {}", self.from, self.until)
+        } else {
+            write!(f, " are only {} min apart", (self.from - self.until) / 60)
+        }
+    }
+}
+
+/// Finds all pairs of trains occupying the same track at overlapping times
+/// or less than `clearance` seconds apart. Occupations without dwell time,
+/// e.g. passes, also conflict with trains exactly `clearance` seconds apart.
+/// Entries without a track are ignored. Conflicts are ordered by station,
+/// track and time.
+pub fn detect(xpln: &Xpln, clearance: i64) -> Vec<Conflict> {
+    let mut tracks = BTreeMap::new();
+
+    for train in xpln.trains.values() {
+        for timetable in train.timetables.iter() {
+            if timetable.track.is_empty() { continue }
+
+            if let Some(occupation) = Occupation::new(train, timetable) {
+                tracks.entry((timetable.station.clone(), timetable.track.clone()))
+                    .or_insert(Vec::new())
+                    .push(occupation);
+            }
+        }
+    }
+
+    let mut conflicts = Vec::new();
+
+    for ((station, track), mut occupations) in tracks.into_iter() {
+        occupations.sort_by(|o0, o1| (o0.start, o0.train).cmp(&(o1.start, o1.train)));
+
+        for (index, first) in occupations.iter().enumerate() {
+            for second in occupations[index + 1..].iter() {
+                // Sorted by start: no later occupation can conflict anymore
+                if second.start > first.end + clearance { break }
+                if second.train == first.train { continue }
+
+                // Back-to-back stops are fine, but a pass at the very moment
+                // another train arrives or leaves is not
+                if second.start == first.end + clearance && first.start < first.end && second.start < second.end {
+                    continue;
+                }
+
+                conflicts.push(Conflict {
+                    station: station.clone(),
+                    track: track.clone(),
+                    first: first.clone(),
+                    second: second.clone(),
+                    from: second.start,
+                    until: if first.end < second.end { first.end } else { second.end }
+                });
+            }
+        }
+    }
+
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use xpln::{Timetable, Train, TrainName, Xpln, XplnTime};
+    use super::detect;
+
+    /// Builds a layout of trains calling at station `A`, each given as
+    /// number, track, arrival and departure.
+    fn xpln(trains: &[(usize, &str, &str, &str)]) -> Xpln {
+        let time = |time: &str| if time.is_empty() { None } else { Some(XplnTime::from_str(time).unwrap()) };
+
+        let mut xpln = Xpln::new();
+
+        for &(number, track, arrival, departure) in trains.iter() {
+            let mut train = Train::new(number, TrainName::parse(&format!("RB {}", number), "", ""), "");
+
+            train.timetables.push(Timetable::new(number, String::from(track), String::from("A"),
+                time(arrival), time(departure), String::new()));
+            xpln.add_train(train);
+        }
+
+        xpln
+    }
+
+    /// Conflicting train pairs found with `clearance` seconds.
+    fn pairs(xpln: &Xpln, clearance: i64) -> Vec<(usize, usize)> {
+        detect(xpln, clearance).iter().map(|conflict| (conflict.first.train, conflict.second.train)).collect()
+    }
+
+    #[test]
+    fn overlapping_stops() {
+        let xpln = xpln(&[(1, "1", "6:00", "6:10"), (2, "1", "6:05", "6:15"), (3, "2", "6:05", "6:15")]);
+        let conflicts = detect(&xpln, 0);
+
+        assert_eq!(1, conflicts.len());
+        assert!(conflicts[0].overlaps());
+        assert_eq!((XplnTime::from_str("6:05").unwrap(), XplnTime::from_str("6:10").unwrap()),
+            (conflicts[0].from, conflicts[0].until));
+    }
+
+    #[test]
+    fn clearance_boundary() {
+        let xpln = xpln(&[(1, "1", "6:00", "6:05"), (2, "1", "6:07", "6:10")]);
+
+        // Exactly the clearance apart is fine, one second more is not
+        assert_eq!(Vec::<(usize, usize)>::new(), pairs(&xpln, 120));
+        assert_eq!(vec![(1, 2)], pairs(&xpln, 121));
+        assert!(!detect(&xpln, 121)[0].overlaps());
+
+        // Back-to-back without clearance
+        let xpln = self::xpln(&[(1, "1", "6:00", "6:05"), (2, "1", "6:05", "6:10")]);
+        assert_eq!(Vec::<(usize, usize)>::new(), pairs(&xpln, 0));
+    }
+
+    #[test]
+    fn simultaneous_passes() {
+        let xpln = xpln(&[(1, "1", "", "6:10"), (2, "1", "", "6:10")]);
+        assert_eq!(vec![(1, 2)], pairs(&xpln, 0));
+
+        // A pass at the moment a stopping train leaves, or the clearance after
+        let xpln = self::xpln(&[(1, "1", "6:00", "6:05"), (2, "1", "6:05", "")]);
+        assert_eq!(vec![(1, 2)], pairs(&xpln, 0));
+
+        let xpln = self::xpln(&[(1, "1", "6:00", "6:05"), (2, "1", "6:07", "")]);
+        assert_eq!(vec![(1, 2)], pairs(&xpln, 120));
+        assert_eq!(Vec::<(usize, usize)>::new(), pairs(&xpln, 119));
+    }
+
+    #[test]
+    fn open_ended_occupations() {
+        // A train ending with an arrival only occupies the track at that time
+        let xpln = xpln(&[(1, "1", "6:00", ""), (2, "1", "", "6:00"), (3, "1", "", "6:02")]);
+        assert_eq!(vec![(1, 2)], pairs(&xpln, 0));
+        assert_eq!(vec![(1, 2), (1, 3), (2, 3)], pairs(&xpln, 120));
+
+        // Entries without time or track are ignored
+        let xpln = self::xpln(&[(1, "1", "", ""), (2, "1", "6:00", "6:05"), (3, "", "6:00", "6:05")]);
+        assert_eq!(Vec::<(usize, usize)>::new(), pairs(&xpln, 0));
+    }
+}
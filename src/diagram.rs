@@ -0,0 +1,238 @@
+use markup::escape;
+use xpln::{Xpln, Train, XplnTime};
+
+/// Colours assigned to train classes in order of their first appearance.
+const COLOURS: [&'static str; 8] = [
+    "#1f77b4", "#d62728", "#2ca02c", "#ff7f0e", "#9467bd", "#8c564b", "#e377c2", "#17becf"
+];
+
+const MARGIN_LEFT: f64 = 60.0;
+const MARGIN_TOP: f64 = 80.0;
+const MARGIN: f64 = 20.0;
+
+/// Width of the plot area the stations are spread across.
+const WIDTH: f64 = 1000.0;
+
+/// Vertical scale of the time axis.
+const PIXELS_PER_MINUTE: f64 = 4.0;
+
+/// Selection of the time-distance graph.
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Stations along the line in drawing order. Two stations not adjacent
+    /// to each other are connected by the shortest path through the lines.
+    /// Empty selects the stations of the train with the longest run.
+    pub stations: Vec<String>,
+    /// Start of the time window, the first time on the line if `None`.
+    pub from: Option<XplnTime>,
+    /// End of the time window, the last time on the line if `None`.
+    pub until: Option<XplnTime>
+}
+
+impl Options {
+    pub fn new() -> Options {
+        Options {
+            stations: Vec::new(),
+            from: None,
+            until: None
+        }
+    }
+}
+
+/// Returns the stations the graph is drawn for, see `Options::stations`.
+pub fn line(xpln: &Xpln, options: &Options) -> Vec<String> {
+    if options.stations.is_empty() {
+        let mut trains = xpln.sorted_trains();
+        trains.sort_by(|t0, t1| t1.timetables.len().cmp(&t0.timetables.len()));
+
+        return match trains.first() {
+            Some(train) => train.timetables.iter().map(|t| t.station.clone()).collect(),
+            None => Vec::new()
+        };
+    }
+
+    let graph = xpln.graph();
+    let mut stations : Vec<String> = Vec::new();
+
+    for station in options.stations.iter() {
+        let path = match stations.last() {
+            Some(last) if !graph.is_adjacent(last, station) => graph.path(last, station),
+            _ => None
+        };
+
+        match path {
+            Some(path) => stations.extend(path.into_iter().skip(1)),
+            None => stations.push(station.clone())
+        }
+    }
+
+    stations
+}
+
+/// Renders the time-distance graph (Bildfahrplan) as SVG: stations across,
+/// time downwards and one polyline per train coloured by train class and
+/// labelled with the train's name.
+///
+/// Stations are spaced by the distance of their positions if all of them
+/// have one, evenly otherwise. Trains are drawn wherever two consecutive
+/// entries of their run are on the selected line. Fails if the time window
+/// ends before it starts.
+pub fn render(xpln: &Xpln, options: &Options) -> Result<String, String> {
+    let stations = line(xpln, options);
+    let offsets  = station_offsets(xpln, &stations);
+
+    let column = |name: &str| stations.iter().position(|s| &s[..] == name);
+
+    // Time window
+    let mut times = Vec::new();
+
+    for train in xpln.trains.values() {
+        for timetable in train.timetables.iter() {
+            if column(&timetable.station).is_some() {
+                times.extend(timetable.arrival);
+                times.extend(timetable.departure);
+            }
+        }
+    }
+
+    times.sort();
+
+    let from = options.from.map(|t| t.relative_to(xpln.session_start))
+        .or(times.first().map(|t| XplnTime::new(0, 0, t.as_minutes() / 60 * 60, 0)))
+        .unwrap_or(xpln.session_start);
+
+    let until = options.until.map(|t| t.relative_to(xpln.session_start))
+        .or(times.last().map(|t| XplnTime::new(0, 0, (t.as_minutes() + 59) / 60 * 60, 0)))
+        .unwrap_or(from + 3600);
+
+    if until <= from {
+        return Err(format!("Time window ends before it starts: {} - {}", from, until));
+    }
+
+    let height = (until - from) as f64 / 60.0 * PIXELS_PER_MINUTE;
+    let x = |index: usize| MARGIN_LEFT + offsets[index] * WIDTH;
+    let y = |time: XplnTime| MARGIN_TOP + (time - from) as f64 / 60.0 * PIXELS_PER_MINUTE;
+
+    let mut classes : Vec<String> = Vec::new();
+    let mut data    = String::new();
+
+    data.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    data.push_str(&format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{:.0}\" font-family=\"sans-serif\" font-size=\"11\">\n",
+        MARGIN_LEFT + WIDTH + MARGIN, MARGIN_TOP + height + MARGIN));
+    data.push_str(&format!("<clipPath id=\"plot\"><rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{:.1}\"/></clipPath>\n",
+        MARGIN_LEFT - MARGIN, MARGIN_TOP, WIDTH + 2.0 * MARGIN, height));
+
+    // Station axis
+    for (index, station) in stations.iter().enumerate() {
+        data.push_str(&format!("<line x1=\"{x:.1}\" y1=\"{}\" x2=\"{x:.1}\" y2=\"{:.1}\" stroke=\"#999\"/>\n",
+            MARGIN_TOP, MARGIN_TOP + height, x=x(index)));
+        data.push_str(&format!("<text x=\"{x:.1}\" y=\"{y}\" transform=\"rotate(-45 {x:.1} {y})\">{}</text>\n",
+            escape(station), x=x(index), y=MARGIN_TOP - 6.0));
+    }
+
+    // Time axis, one line every ten minutes starting at the first full step
+    let mut time = XplnTime::from_seconds((from.as_seconds() + 599) / 600 * 600);
+
+    while time <= until {
+        let hour = time.minute() == 0;
+
+        data.push_str(&format!("<line x1=\"{}\" y1=\"{y:.1}\" x2=\"{}\" y2=\"{y:.1}\" stroke=\"{}\"/>\n",
+            MARGIN_LEFT, MARGIN_LEFT + WIDTH, if hour { "#999" } else { "#ddd" }, y=y(time)));
+
+        if hour {
+            data.push_str(&format!("<text x=\"{}\" y=\"{:.1}\">{}</text>\n", MARGIN, y(time) + 4.0, time));
+        }
+
+        time = time + 600;
+    }
+
+    // Trains
+    data.push_str("<g clip-path=\"url(#plot)\" fill=\"none\" stroke-width=\"1.5\">\n");
+
+    for train in xpln.sorted_trains() {
+        if !classes.iter().any(|c| &c[..] == train.class()) {
+            classes.push(String::from(train.class()));
+        }
+
+        let index  = classes.iter().position(|c| &c[..] == train.class()).unwrap();
+        let colour = COLOURS[index % COLOURS.len()];
+
+        for points in segments(train, &column) {
+            let path : Vec<String> = points.iter()
+                .map(|&(index, time)| format!("{:.1},{:.1}", x(index), y(time)))
+                .collect();
+
+            data.push_str(&format!("<polyline stroke=\"{}\" points=\"{}\"/>\n", colour, path.join(" ")));
+
+            let (x0, y0) = (x(points[0].0), y(points[0].1));
+            data.push_str(&format!("<text x=\"{:.1}\" y=\"{:.1}\" fill=\"{}\" stroke=\"none\">{}</text>\n",
+                x0 + 3.0, y0 - 3.0, colour, escape(&train.name())));
+        }
+    }
+
+    data.push_str("</g>\n");
+
+    // Legend
+    for (index, class) in classes.iter().enumerate() {
+        data.push_str(&format!("<text x=\"{}\" y=\"{}\" fill=\"{}\">{}</text>\n",
+            MARGIN_LEFT + WIDTH - 60.0, MARGIN + 12.0 * index as f64, COLOURS[index % COLOURS.len()], escape(class)));
+    }
+
+    data.push_str("</svg>\n");
+    Ok(data)
+}
+
+/// Relative position of each station on the axis between `0` and `1`.
+fn station_offsets(xpln: &Xpln, stations: &[String]) -> Vec<f64> {
+    let mut distances = vec![0.0];
+
+    for pair in stations.windows(2) {
+        let distance = match (xpln.station(&pair[0]), xpln.station(&pair[1])) {
+            (Some(s0), Some(s1)) => s0.distance(s1),
+            _ => None
+        };
+
+        match distance {
+            Some(distance) if distance > 0.0 => distances.push(distance),
+            _ => { distances = vec![1.0; stations.len()]; break }
+        }
+    }
+
+    let total : f64 = distances.iter().skip(1).fold(0.0, |sum, d| sum + d);
+    let mut offset = 0.0;
+
+    distances.iter().enumerate().map(|(index, distance)| {
+        if index > 0 { offset += *distance }
+        if total > 0.0 { offset / total } else { 0.5 }
+    }).collect()
+}
+
+/// Splits the train's run into the parts on the selected line, each as
+/// points of station column and time. Consecutive entries must be at
+/// adjacent columns to be connected.
+fn segments<F: Fn(&str) -> Option<usize>>(train: &Train, column: &F) -> Vec<Vec<(usize, XplnTime)>> {
+    let mut segments = Vec::new();
+    let mut current : Vec<(usize, XplnTime)> = Vec::new();
+
+    for timetable in train.timetables.iter() {
+        let index = column(&timetable.station);
+
+        let connected = match (current.last(), index) {
+            (Some(&(last, _)), Some(index)) => last + 1 == index || index + 1 == last || last == index,
+            _ => false
+        };
+
+        if !connected && current.len() > 1 { segments.push(current.clone()) }
+        if !connected { current.clear() }
+
+        if let Some(index) = index {
+            for time in [timetable.arrival, timetable.departure].iter().filter_map(|time| *time) {
+                if current.last() != Some(&(index, time)) { current.push((index, time)) }
+            }
+        }
+    }
+
+    if current.len() > 1 { segments.push(current) }
+
+    segments
+}
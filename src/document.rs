@@ -0,0 +1,227 @@
+use std::io;
+use std::io::Read;
+use std::error::Error as _StdError;
+
+use yaml_rust::{Yaml, YamlLoader};
+use yaml_rust::scanner::ScanError;
+
+use network::{self, Line};
+use ods::{Cell, Spreadsheet, Table};
+use xpln::{TrainName, STATION_TRACK_TABLE, TRAINS_HEADER, TRAINS_TABLE};
+
+#[derive(Debug)]
+pub struct Error {
+    description: String
+}
+
+impl ::std::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(fmt, "{}", self.description)
+    }
+}
+
+impl ::std::error::Error for Error {
+    fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error { description: String::from(err.description()) }
+    }
+}
+
+impl From<ScanError> for Error {
+    fn from(err: ScanError) -> Error {
+        Error { description: format!("Parser error: {}", err) }
+    }
+}
+
+impl From<String> for Error {
+    fn from(err: String) -> Error {
+        Error { description: err }
+    }
+}
+
+/// Reads a JSON or YAML timetable document, see `parse_str`.
+pub fn parse<R: Read>(mut reader: R) -> Result<Spreadsheet, Error> {
+    let mut text = String::new();
+    try!(reader.read_to_string(&mut text));
+
+    parse_str(&text)
+}
+
+/// Reads a JSON or YAML timetable document with the structure written by
+/// `json::render` into the StationTrack and Trains tables of a spreadsheet,
+/// so it can be loaded and checked like an XPLN spreadsheet.
+///
+/// Only values that are also given in the spreadsheet are read; derived
+/// values like track flags, stop kinds and diagnostics are ignored. Rows are
+/// numbered in document order after a header row, with the traindef followed
+/// by the train's group, wheel and timetable rows.
+pub fn parse_str(text: &str) -> Result<Spreadsheet, Error> {
+    let documents = try!(YamlLoader::load_from_str(text));
+
+    let document = match documents.first() {
+        Some(document) => document,
+        None => return Err(Error::from(String::from("Empty document.")))
+    };
+
+    let mut spreadsheet = Spreadsheet::new();
+
+    spreadsheet.push(try!(station_track_table(document)));
+    spreadsheet.push(try!(trains_table(document)));
+
+    Ok(spreadsheet)
+}
+
+fn station_track_table(document: &Yaml) -> Result<Table, Error> {
+    let mut table = Table::new(STATION_TRACK_TABLE);
+
+    table.push(strings(&network::STATION_TRACK_HEADER));
+
+    let mut lines = Vec::new();
+
+    for line in try!(list(document, "lines")).iter() {
+        let id = match try!(cell(line, "id")).as_usize() {
+            Some(id) => id,
+            None => return Err(Error::from(format!("Invalid line id: {:?}", line["id"])))
+        };
+
+        lines.push(Line::new(id as u32, try!(cell(line, "from")).to_string(), try!(cell(line, "to")).to_string()));
+    }
+
+    let lines = network::encode_lines(&lines);
+
+    for (index, station) in try!(list(document, "stations")).iter().enumerate() {
+        let name = try!(cell(station, "name"));
+
+        table.push(vec![
+            name.clone(),
+            Cell::Empty,
+            try!(cell(&station["position"], "x")),
+            try!(cell(&station["position"], "y")),
+            try!(cell(station, "remark")),
+            Cell::String(String::from("Station")),
+            Cell::Empty,
+            Cell::Empty,
+            if index == 0 && !lines.is_empty() { Cell::String(lines.clone()) } else { Cell::Empty }
+        ]);
+
+        for track in try!(list(station, "tracks")).iter() {
+            table.push(vec![
+                name.clone(),
+                Cell::Empty,
+                try!(cell(track, "name")),
+                try!(cell(track, "length")),
+                Cell::Empty,
+                Cell::String(String::from("Track")),
+                try!(cell(track, "kind")),
+                try!(cell(track, "owner"))
+            ]);
+        }
+    }
+
+    Ok(table)
+}
+
+fn trains_table(document: &Yaml) -> Result<Table, Error> {
+    let mut table = Table::new(TRAINS_TABLE);
+
+    table.push(strings(&TRAINS_HEADER));
+
+    for train in try!(list(document, "trains")).iter() {
+        let number = try!(cell(train, "number"));
+        let (name, prefix, suffix) = try!(designation(train));
+
+        table.push(vec![
+            number.clone(), Cell::Empty, prefix, suffix, Cell::Empty, Cell::Empty,
+            try!(cell(train, "speed")), Cell::Empty, Cell::String(String::from("traindef")),
+            name, try!(cell(train, "remark"))
+        ]);
+
+        if !train["group"].is_badvalue() && !train["group"].is_null() {
+            table.push(vec![
+                number.clone(), Cell::Empty, Cell::Empty, Cell::Empty, Cell::Empty, Cell::Empty,
+                try!(cell(&train["group"], "id")), try!(cell(&train["group"], "name")),
+                Cell::String(String::from("group"))
+            ]);
+        }
+
+        if !train["wheels"].is_badvalue() && !train["wheels"].is_null() {
+            table.push(vec![
+                number.clone(), Cell::Empty, Cell::Empty, Cell::Empty, Cell::Empty, Cell::Empty,
+                try!(cell(train, "wheels")), Cell::Empty, Cell::String(String::from("wheel"))
+            ]);
+        }
+
+        for timetable in try!(list(train, "timetable")).iter() {
+            table.push(vec![
+                number.clone(),
+                try!(cell(timetable, "sequence")),
+                try!(cell(timetable, "station")),
+                try!(cell(timetable, "track")),
+                try!(cell(timetable, "arrival")),
+                try!(cell(timetable, "departure")),
+                Cell::Empty,
+                Cell::Empty,
+                Cell::String(String::from("timetable")),
+                Cell::Empty,
+                try!(cell(timetable, "remark"))
+            ]);
+        }
+    }
+
+    Ok(table)
+}
+
+/// Returns the traindef's `Trainname`, prefix and suffix columns. The
+/// `designation` object's `original` includes prefix and suffix, which are
+/// stripped again; without a designation the train's `name` is used.
+fn designation(train: &Yaml) -> Result<(Cell, Cell, Cell), Error> {
+    let designation = &train["designation"];
+
+    if designation.is_badvalue() || designation.is_null() {
+        return Ok((try!(cell(train, "name")), Cell::Empty, Cell::Empty));
+    }
+
+    let prefix = try!(cell(designation, "prefix")).to_string();
+    let suffix = try!(cell(designation, "suffix")).to_string();
+
+    let name = TrainName {
+        original: String::from(designation["original"].as_str().unwrap_or("")),
+        category: String::new(),
+        line: None,
+        prefix: if prefix.is_empty() { None } else { Some(prefix.clone()) },
+        suffix: if suffix.is_empty() { None } else { Some(suffix.clone()) }
+    };
+
+    Ok((Cell::String(String::from(name.sheet_name())), Cell::String(prefix), Cell::String(suffix)))
+}
+
+/// Returns the array `key` of `node`, empty if it is missing or `null`.
+fn list<'a>(node: &'a Yaml, key: &str) -> Result<&'a [Yaml], Error> {
+    match node[key] {
+        Yaml::Array(ref items) => Ok(items),
+        Yaml::Null | Yaml::BadValue => Ok(&[]),
+        _ => Err(Error::from(format!("Expected a list for {}.", key)))
+    }
+}
+
+/// Converts the scalar `key` of `node` into a spreadsheet cell. Missing
+/// values and `null` are empty cells.
+fn cell(node: &Yaml, key: &str) -> Result<Cell, Error> {
+    match node[key] {
+        Yaml::Null | Yaml::BadValue => Ok(Cell::Empty),
+        Yaml::Integer(value) => Ok(Cell::Float(value as f64)),
+        Yaml::Real(_) => Ok(node[key].as_f64().map(Cell::Float).unwrap_or(Cell::Empty)),
+        Yaml::String(ref value) => Ok(Cell::String(value.clone())),
+        Yaml::Boolean(value) => Ok(Cell::Boolean(value)),
+        _ => Err(Error::from(format!("Expected a single value for {}.", key)))
+    }
+}
+
+fn strings(values: &[&str]) -> Vec<Cell> {
+    values.iter().map(|value| Cell::String(String::from(*value))).collect()
+}
@@ -0,0 +1,127 @@
+use markup::escape;
+use xpln::{Xpln, StopKind, Train, Timetable, XplnTime};
+
+/// Output format of the driver's timetable.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    Text,
+    Html
+}
+
+impl Format {
+    /// File extension of documents in this format.
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            Format::Text => "txt",
+            Format::Html => "html"
+        }
+    }
+}
+
+/// Renders the driver's timetable (Buchfahrplan) for `train` in `format`.
+/// Passes are marked with `pass_marker`, as in the BFOs.
+pub fn render(xpln: &Xpln, train: &Train, format: Format, pass_marker: &str) -> String {
+    match format {
+        Format::Text => render_text(xpln, train, pass_marker),
+        Format::Html => render_html(xpln, train, pass_marker)
+    }
+}
+
+/// Renders the driver's timetable as plain text: a header with the train's
+/// designation, remark and speed followed by one line per station in order
+/// of the run. Passes are written with `pass_marker` as arrival. Labels are
+/// German, like the BFOs and the spreadsheet.
+pub fn render_text(xpln: &Xpln, train: &Train, pass_marker: &str) -> String {
+    let mut data = String::new();
+
+    data.push_str(&format!("{}\n", train.original_name()));
+
+    if !train.remark.is_empty() {
+        data.push_str(&format!("{}\n", train.remark));
+    }
+
+    if let Some(speed) = train.speed {
+        data.push_str(&format!("Höchstgeschwindigkeit: {} km/h\n", speed));
+    }
+
+    data.push_str("\n");
+    data.push_str(&format!("{:<6} {:<24} {:<5}  {:<5}  {:<5}  {}\n", "", "Bahnhof", "an", "ab", "Gleis", "Bemerkung"));
+
+    for timetable in train.timetables.iter() {
+        let (arrival, departure) = times(timetable, pass_marker);
+
+        let line = format!("{:<6} {:<24} {:<5}  {:<5}  {:<5}  {}",
+            timetable.station, station_name(xpln, timetable), arrival, departure,
+            timetable.track, timetable.remark
+        );
+
+        data.push_str(line.trim_right());
+        data.push_str("\n");
+    }
+
+    data
+}
+
+/// Renders the driver's timetable as a standalone HTML page with the same
+/// content as `render_text`, suitable for printing and tablets.
+pub fn render_html(xpln: &Xpln, train: &Train, pass_marker: &str) -> String {
+    let mut data = String::new();
+
+    data.push_str("<!DOCTYPE html>\n");
+    data.push_str("<html lang=\"de\">\n<head>\n");
+    data.push_str("<meta charset=\"utf-8\">\n");
+    data.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    data.push_str(&format!("<title>{}</title>\n", escape(&train.original_name())));
+    data.push_str("<style>\n");
+    data.push_str("body { font-family: sans-serif; }\n");
+    data.push_str("table { border-collapse: collapse; width: 100%; }\n");
+    data.push_str("th, td { border: 1px solid #999; padding: 0.3em 0.5em; text-align: left; }\n");
+    data.push_str("td.time { font-family: monospace; }\n");
+    data.push_str("tr.pass { color: #666; }\n");
+    data.push_str("</style>\n");
+    data.push_str("</head>\n<body>\n");
+
+    data.push_str(&format!("<h1>{}</h1>\n", escape(&train.original_name())));
+
+    if !train.remark.is_empty() {
+        data.push_str(&format!("<p>{}</p>\n", escape(&train.remark)));
+    }
+
+    if let Some(speed) = train.speed {
+        data.push_str(&format!("<p>Höchstgeschwindigkeit: {} km/h</p>\n", speed));
+    }
+
+    data.push_str("<table>\n");
+    data.push_str("<tr><th></th><th>Bahnhof</th><th>an</th><th>ab</th><th>Gleis</th><th>Bemerkung</th></tr>\n");
+
+    for timetable in train.timetables.iter() {
+        let (arrival, departure) = times(timetable, pass_marker);
+
+        data.push_str(&format!(
+            "<tr{}><td>{}</td><td>{}</td><td class=\"time\">{}</td><td class=\"time\">{}</td><td>{}</td><td>{}</td></tr>\n",
+            if timetable.kind == StopKind::Pass { " class=\"pass\"" } else { "" },
+            escape(&timetable.station), escape(station_name(xpln, timetable)),
+            escape(&arrival), escape(&departure),
+            escape(&timetable.track), escape(&timetable.remark)
+        ));
+    }
+
+    data.push_str("</table>\n");
+    data.push_str("</body>\n</html>\n");
+
+    data
+}
+
+fn times(timetable: &Timetable, pass_marker: &str) -> (String, String) {
+    match timetable.kind {
+        StopKind::Pass => (String::from(pass_marker), XplnTime::format_hm(timetable.departure.or(timetable.arrival), "")),
+        StopKind::Origin => (String::new(), XplnTime::format_hm(timetable.departure.or(timetable.arrival), "")),
+        StopKind::Terminus => (XplnTime::format_hm(timetable.arrival.or(timetable.departure), ""), String::new()),
+        _ => (XplnTime::format_hm(timetable.arrival, ""), XplnTime::format_hm(timetable.departure, ""))
+    }
+}
+
+/// Full station name from the station's remark, empty for unknown stations.
+fn station_name<'a>(xpln: &'a Xpln, timetable: &Timetable) -> &'a str {
+    xpln.station(&timetable.station).map(|station| &station.remark[..]).unwrap_or("")
+}
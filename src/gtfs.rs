@@ -0,0 +1,151 @@
+use std::collections::BTreeSet;
+use std::io::{Seek, Write};
+
+use zip::{CompressionMethod, ZipWriter};
+use zip::result::ZipResult;
+
+use xpln::{Xpln, StopKind, Train};
+
+/// Feed information not contained in the spreadsheet.
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub agency_name: String,
+    pub agency_url: String,
+    pub timezone: String,
+    /// First day of service as `YYYYMMDD`.
+    pub start_date: String,
+    /// Last day of service as `YYYYMMDD`.
+    pub end_date: String
+}
+
+impl Options {
+    pub fn new() -> Options {
+        Options {
+            agency_name: String::from("XPLN"),
+            agency_url: String::from("https://github.com/jgraichen/xpln2bfo"),
+            timezone: String::from("Europe/Berlin"),
+            start_date: String::from("20000101"),
+            end_date: String::from("20991231")
+        }
+    }
+}
+
+/// Writes the session timetable as GTFS feed into a zip archive: one stop
+/// per station, one route per train class, one trip per train and its
+/// timetable entries as stop times, all running on a single service every
+/// day.
+///
+/// Trains without any timed entry are left out. Stations only known from
+/// timetables, i.e. missing in StationTrack, are written as stops without
+/// position so that every stop time refers to a stop.
+///
+/// Times after midnight of the session start day are written as 24:00:00
+/// and later. Passes are written with pickup and drop off disabled. Station
+/// positions are given in layout units and written as coordinates around
+/// 0/0 so tools can draw the layout.
+pub fn write<W: Write + Seek>(xpln: &Xpln, options: &Options, writer: W) -> ZipResult<W> {
+    let mut zip = ZipWriter::new(writer);
+
+    try!(zip.start_file("agency.txt", CompressionMethod::Deflated));
+    try!(write_record(&mut zip, &["agency_id", "agency_name", "agency_url", "agency_timezone"]));
+    try!(write_record(&mut zip, &["xpln", &options.agency_name, &options.agency_url, &options.timezone]));
+
+    try!(zip.start_file("calendar.txt", CompressionMethod::Deflated));
+    try!(write_record(&mut zip, &["service_id", "monday", "tuesday", "wednesday", "thursday", "friday",
+        "saturday", "sunday", "start_date", "end_date"]));
+    try!(write_record(&mut zip, &["session", "1", "1", "1", "1", "1", "1", "1",
+        &options.start_date, &options.end_date]));
+
+    try!(zip.start_file("stops.txt", CompressionMethod::Deflated));
+    try!(write_record(&mut zip, &["stop_id", "stop_code", "stop_name", "stop_lat", "stop_lon"]));
+
+    let trains : Vec<&Train> = xpln.sorted_trains().into_iter()
+        .filter(|train| train.timetables.iter().any(|t| t.arrival.is_some() || t.departure.is_some()))
+        .collect();
+
+    let mut stops : BTreeSet<&str> = xpln.stations.keys().map(|name| &name[..]).collect();
+
+    for train in trains.iter() {
+        stops.extend(train.timetables.iter().map(|timetable| &timetable.station[..]));
+    }
+
+    for stop in stops.iter() {
+        let (lat, lon) = match xpln.station(stop).and_then(|station| station.position) {
+            Some(position) => (-position.y / 100000.0, position.x / 100000.0),
+            None => (0.0, 0.0)
+        };
+
+        try!(write_record(&mut zip, &[stop, stop, stop_name(xpln, stop),
+            &format!("{:.6}", lat), &format!("{:.6}", lon)]));
+    }
+
+    let mut classes : Vec<&str> = trains.iter().map(|train| train.class()).collect();
+    classes.sort();
+    classes.dedup();
+
+    try!(zip.start_file("routes.txt", CompressionMethod::Deflated));
+    try!(write_record(&mut zip, &["route_id", "agency_id", "route_short_name", "route_type"]));
+
+    for class in classes.iter() {
+        try!(write_record(&mut zip, &[class, "xpln", class, "2"]));
+    }
+
+    try!(zip.start_file("trips.txt", CompressionMethod::Deflated));
+    try!(write_record(&mut zip, &["route_id", "service_id", "trip_id", "trip_short_name", "trip_headsign"]));
+
+    for train in trains.iter() {
+        let headsign = match train.timetables.last() {
+            Some(last) => stop_name(xpln, &last.station),
+            None => ""
+        };
+
+        try!(write_record(&mut zip, &[train.class(), "session", &train.number.to_string(),
+            &train.name(), headsign]));
+    }
+
+    try!(zip.start_file("stop_times.txt", CompressionMethod::Deflated));
+    try!(write_record(&mut zip, &["trip_id", "arrival_time", "departure_time", "stop_id", "stop_sequence",
+        "stop_headsign", "pickup_type", "drop_off_type"]));
+
+    for train in trains.iter() {
+        for (index, timetable) in train.timetables.iter().enumerate() {
+            let (arrival, departure) = match (timetable.arrival, timetable.departure) {
+                (Some(arrival), Some(departure)) => (arrival, departure),
+                (Some(time), None) | (None, Some(time)) => (time, time),
+                (None, None) => continue
+            };
+
+            let service = if timetable.kind == StopKind::Pass { "1" } else { "0" };
+
+            try!(write_record(&mut zip, &[&train.number.to_string(), &arrival.format_hms(),
+                &departure.format_hms(), &timetable.station, &(index + 1).to_string(),
+                "", service, service]));
+        }
+    }
+
+    zip.finish()
+}
+
+/// Full name of the station from its remark, or its abbreviation.
+fn stop_name<'a>(xpln: &'a Xpln, station: &'a str) -> &'a str {
+    match xpln.station(station) {
+        Some(station) if !station.remark.is_empty() => &station.remark,
+        _ => station
+    }
+}
+
+/// Writes one CSV record, quoting fields containing separators or quotes.
+fn write_record<W: Write>(writer: &mut W, fields: &[&str]) -> ZipResult<()> {
+    let fields : Vec<String> = fields.iter().map(|field| {
+        if field.contains(',') || field.contains('"') || field.contains('\n') {
+            format!("\"{}\"", field.replace("\"", "\"\""))
+        } else {
+            String::from(*field)
+        }
+    }).collect();
+
+    try!(writer.write_all(fields.join(",").as_bytes()));
+    try!(writer.write_all(b"\r\n"));
+
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use std::fmt::Write;
+
+use report::Diagnostic;
+use xpln::{Xpln, Station, Track, Train, Timetable, StopKind};
+
+/// Version of the document structure written by `render`, see
+/// `doc/json.md`. Incremented on changes that are not backwards compatible.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// JSON value.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    /// Members in the order they are written.
+    Object(Vec<(String, Value)>)
+}
+
+impl Value {
+    fn string<S: Into<String>>(value: S) -> Value {
+        Value::String(value.into())
+    }
+
+    fn option<T, F: Fn(T) -> Value>(value: Option<T>, f: F) -> Value {
+        match value {
+            Some(value) => f(value),
+            None => Value::Null
+        }
+    }
+
+    /// Writes the value indented by two spaces per level.
+    pub fn write(&self, out: &mut String, indent: usize) {
+        match *self {
+            Value::Null => out.push_str("null"),
+            Value::Bool(value) => out.push_str(if value { "true" } else { "false" }),
+            Value::Number(value) if value.is_finite() => { write!(out, "{}", value).unwrap(); },
+            Value::Number(_) => out.push_str("null"),
+            Value::String(ref value) => write_string(out, value),
+            Value::Array(ref values) if values.is_empty() => out.push_str("[]"),
+            Value::Array(ref values) => {
+                out.push_str("[\n");
+
+                for (index, value) in values.iter().enumerate() {
+                    push_indent(out, indent + 1);
+                    value.write(out, indent + 1);
+                    out.push_str(if index + 1 < values.len() { ",\n" } else { "\n" });
+                }
+
+                push_indent(out, indent);
+                out.push_str("]");
+            },
+            Value::Object(ref members) if members.is_empty() => out.push_str("{}"),
+            Value::Object(ref members) => {
+                out.push_str("{\n");
+
+                for (index, &(ref key, ref value)) in members.iter().enumerate() {
+                    push_indent(out, indent + 1);
+                    write_string(out, key);
+                    out.push_str(": ");
+                    value.write(out, indent + 1);
+                    out.push_str(if index + 1 < members.len() { ",\n" } else { "\n" });
+                }
+
+                push_indent(out, indent);
+                out.push_str("}");
+            }
+        }
+    }
+}
+
+/// Renders the complete model and the given diagnostics as JSON document.
+pub fn render(xpln: &Xpln, diagnostics: &[Diagnostic]) -> String {
+    let mut data = String::new();
+
+    to_json(xpln, diagnostics).write(&mut data, 0);
+    data.push_str("\n");
+
+    data
+}
+
+/// Builds the JSON document for the model as described in `doc/json.md`.
+pub fn to_json(xpln: &Xpln, diagnostics: &[Diagnostic]) -> Value {
+    object(vec![
+        ("version", Value::Number(SCHEMA_VERSION as f64)),
+        ("session_start", Value::string(xpln.session_start.format_hms())),
+        ("stations", Value::Array(xpln.sorted_stations().into_iter().map(station).collect())),
+        ("lines", Value::Array(xpln.lines.iter().map(|line| object(vec![
+            ("id", Value::Number(line.id as f64)),
+            ("from", Value::string(line.from.clone())),
+            ("to", Value::string(line.to.clone()))
+        ])).collect())),
+        ("trains", Value::Array(xpln.sorted_trains().into_iter().map(train).collect())),
+        ("diagnostics", Value::Array(diagnostics.iter().map(diagnostic).collect()))
+    ])
+}
+
+fn station(station: &Station) -> Value {
+    object(vec![
+        ("name", Value::string(station.name.clone())),
+        ("remark", Value::string(station.remark.clone())),
+        ("position", Value::option(station.position, |position| object(vec![
+            ("x", Value::Number(position.x)),
+            ("y", Value::Number(position.y))
+        ]))),
+        ("tracks", Value::Array(station.tracks.iter().map(track).collect()))
+    ])
+}
+
+fn track(track: &Track) -> Value {
+    object(vec![
+        ("name", Value::string(track.name.clone())),
+        ("kind", Value::string(track.kind.name())),
+        ("owner", Value::string(track.owner.clone())),
+        ("length", Value::option(track.length, Value::Number)),
+        ("platform", Value::Bool(track.platform)),
+        ("through", Value::Bool(track.through))
+    ])
+}
+
+fn train(train: &Train) -> Value {
+    let designation = &train.designation;
+
+    object(vec![
+        ("number", Value::Number(train.number as f64)),
+        ("name", Value::string(train.name())),
+        ("designation", object(vec![
+            ("original", Value::string(designation.original.clone())),
+            ("category", Value::string(designation.category.clone())),
+            ("line", Value::option(designation.line.clone(), Value::String)),
+            ("prefix", Value::option(designation.prefix.clone(), Value::String)),
+            ("suffix", Value::option(designation.suffix.clone(), Value::String))
+        ])),
+        ("remark", Value::string(train.remark.clone())),
+        ("speed", Value::option(train.speed, |speed| Value::Number(speed as f64))),
+        ("group", Value::option(train.group.as_ref(), |group| object(vec![
+            ("id", Value::Number(group.id as f64)),
+            ("name", Value::string(group.name.clone()))
+        ]))),
+        ("wheels", Value::option(train.wheels, |wheels| Value::Number(wheels as f64))),
+        ("successor", Value::option(train.successor, |successor| Value::Number(successor as f64))),
+        ("timetable", Value::Array(train.timetables.iter().map(timetable).collect()))
+    ])
+}
+
+fn timetable(timetable: &Timetable) -> Value {
+    let kind = match timetable.kind {
+        StopKind::Origin => "origin",
+        StopKind::Terminus => "terminus",
+        StopKind::Stop => "stop",
+        StopKind::Pass => "pass",
+        StopKind::Reverse => "reverse"
+    };
+
+    object(vec![
+        ("sequence", Value::option(timetable.sequence, |sequence| Value::Number(sequence as f64))),
+        ("station", Value::string(timetable.station.clone())),
+        ("track", Value::string(timetable.track.clone())),
+        ("arrival", Value::option(timetable.arrival, |time| Value::string(time.format_hms()))),
+        ("departure", Value::option(timetable.departure, |time| Value::string(time.format_hms()))),
+        ("kind", Value::string(kind)),
+        ("remark", Value::string(timetable.remark.clone()))
+    ])
+}
+
+fn diagnostic(diagnostic: &Diagnostic) -> Value {
+    object(vec![
+        ("severity", Value::string(diagnostic.severity.to_string())),
+        ("code", Value::string(diagnostic.code)),
+        ("message", Value::string(diagnostic.message.clone())),
+        ("sheet", Value::string(diagnostic.sheet.clone())),
+        ("row", Value::option(diagnostic.row, |row| Value::Number(row as f64))),
+        ("column", Value::option(diagnostic.column, |column| Value::Number(column as f64)))
+    ])
+}
+
+fn object(members: Vec<(&str, Value)>) -> Value {
+    Value::Object(members.into_iter().map(|(key, value)| (String::from(key), value)).collect())
+}
+
+fn push_indent(out: &mut String, indent: usize) {
+    for _ in 0..indent {
+        out.push_str("  ");
+    }
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => { write!(out, "\\u{:04x}", c as u32).unwrap(); },
+            c => out.push(c)
+        }
+    }
+
+    out.push('"');
+}
@@ -0,0 +1,28 @@
+//! Reads XPLN timetable spreadsheets and converts them to BFO text documents
+//! and RgZm configuration.
+//!
+//! `ods::parse` reads an OpenDocument spreadsheet, `document::parse` converts
+//! a JSON or YAML document into one, `xpln::Xpln::load` builds the station
+//! and train model from it, reporting problems as `report::Diagnostic`s, and
+//! the `bfo` and `rgzm` modules render the model.
+
+extern crate zip;
+extern crate xml;
+extern crate yaml_rust;
+
+pub mod bfo;
+pub mod circulation;
+pub mod conflicts;
+pub mod diagram;
+pub mod document;
+pub mod driver;
+pub mod gtfs;
+pub mod json;
+pub mod markup;
+pub mod network;
+pub mod ods;
+pub mod report;
+pub mod rgzm;
+pub mod time;
+pub mod validate;
+pub mod xpln;
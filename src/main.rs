@@ -1,13 +1,182 @@
-extern crate zip;
-extern crate xml;
+extern crate xpln2bfo;
 
 use std::io::prelude::*;
 use std::fs;
 use std::fs::File;
 use std::path::PathBuf;
 
-mod ods;
-mod xpln;
+use xpln2bfo::{bfo, circulation, conflicts, diagram, document, driver, gtfs, json, ods, rgzm, validate, xpln};
+use xpln2bfo::report::{LoadReport, Severity};
+
+const USAGE: &'static str = "\
+Usage: {program} [convert] [options] <input> [<outdir>]
+       {program} validate [options] <input>
+       {program} conflicts [--clearance <minutes>] [options] <input>
+       {program} circulation [options] <input>
+
+Options:
+    --session-start <HH:MM>               Start of the session (default 00:00)
+    --endpoints blank|markers|yard:<name> Previous/next station of first/last stops
+    --train-names short|original          Category and number or full train designation
+    --fiddle-yard <station>               Handle station as fiddle yard (repeatable)
+    --pass-marker <text>                  Arrival column of trains passing without a stop (default |)
+    --rgzm                                Also write RgZm configuration
+    --driver text|html                    Also write a driver's timetable per train
+    --gtfs                                Also write a GTFS feed
+    --json                                Also write the model and diagnostics as JSON
+    --ods                                 Also write the model as XPLN spreadsheet
+    --svg                                 Also write a time-distance graph
+    --line <station>,<station>,...        Stations of the time-distance graph
+    --from <HH:MM>, --until <HH:MM>       Time window of the time-distance graph
+    --clearance <minutes>                 Minimum time between trains on a track (default 0)";
+
+#[derive(PartialEq, Debug)]
+enum Command {
+	Convert,
+	Validate,
+	Conflicts,
+	Circulation
+}
+
+struct Options {
+	command: Command,
+	input: PathBuf,
+	outdir: Option<PathBuf>,
+	session_start: xpln::XplnTime,
+	bfo: bfo::Options,
+	rgzm: bool,
+	driver: Option<driver::Format>,
+	gtfs: bool,
+	json: bool,
+	ods: bool,
+	svg: bool,
+	diagram: diagram::Options,
+	clearance: i64
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+	let mut paths         = Vec::new();
+	let mut session_start = xpln::XplnTime::new(0, 0, 0, 0);
+	let mut bfo           = bfo::Options::new();
+	let mut rgzm          = false;
+	let mut driver        = None;
+	let mut gtfs          = false;
+	let mut json          = false;
+	let mut ods           = false;
+	let mut svg           = false;
+	let mut diagram       = diagram::Options::new();
+	let mut clearance     = 0;
+	let mut iter          = args.iter().peekable();
+
+	let command = match iter.peek().map(|arg| arg.as_ref()) {
+		Some("convert") => { iter.next(); Command::Convert },
+		Some("validate") => { iter.next(); Command::Validate },
+		Some("conflicts") => { iter.next(); Command::Conflicts },
+		Some("circulation") => { iter.next(); Command::Circulation },
+		_ => Command::Convert
+	};
+
+	while let Some(arg) = iter.next() {
+		match arg.as_ref() {
+			"--session-start" => {
+				session_start = try!(try!(option_value(&mut iter, arg)).parse());
+			},
+			"--endpoints" => {
+				let value = try!(option_value(&mut iter, arg));
+
+				bfo.endpoints = match value.as_ref() {
+					"blank" => bfo::Endpoints::Blank,
+					"markers" => bfo::Endpoints::Markers,
+					_ if value.starts_with("yard:") => bfo::Endpoints::FiddleYard(String::from(&value[5..])),
+					_ => return Err(format!("Invalid value for {}: {}", arg, value))
+				};
+			},
+			"--train-names" => {
+				let value = try!(option_value(&mut iter, arg));
+
+				bfo.train_names = match value.as_ref() {
+					"short" => bfo::TrainNames::Short,
+					"original" => bfo::TrainNames::Original,
+					_ => return Err(format!("Invalid value for {}: {}", arg, value))
+				};
+			},
+			"--fiddle-yard" => {
+				bfo.fiddle_yards.push(try!(option_value(&mut iter, arg)).clone());
+			},
+			"--pass-marker" => {
+				bfo.pass_marker = try!(option_value(&mut iter, arg)).clone();
+			},
+			"--rgzm" => rgzm = true,
+			"--gtfs" => gtfs = true,
+			"--json" => json = true,
+			"--ods" => ods = true,
+			"--svg" => svg = true,
+			"--line" => {
+				let value = try!(option_value(&mut iter, arg));
+
+				diagram.stations = value.split(',').map(|name| String::from(name.trim()))
+					.filter(|name| !name.is_empty())
+					.collect();
+			},
+			"--from" => {
+				diagram.from = Some(try!(try!(option_value(&mut iter, arg)).parse()));
+			},
+			"--until" => {
+				diagram.until = Some(try!(try!(option_value(&mut iter, arg)).parse()));
+			},
+			"--driver" => {
+				let value = try!(option_value(&mut iter, arg));
+
+				driver = match value.as_ref() {
+					"text" => Some(driver::Format::Text),
+					"html" => Some(driver::Format::Html),
+					_ => return Err(format!("Invalid value for {}: {}", arg, value))
+				};
+			},
+			"--clearance" => {
+				let value = try!(option_value(&mut iter, arg));
+
+				clearance = match value.parse::<i64>() {
+					Ok(minutes) if minutes >= 0 => minutes * 60,
+					_ => return Err(format!("Invalid value for {}: {}", arg, value))
+				};
+			},
+			_ if arg.starts_with("--") => return Err(format!("Unknown option {}.", arg)),
+			_ => paths.push(PathBuf::from(arg))
+		}
+	}
+
+	if command != Command::Convert && paths.len() != 1 {
+		return Err(String::from("Expected an input file."));
+	}
+
+	if paths.len() < 1 || paths.len() > 2 {
+		return Err(String::from("Expected an input file and an optional output directory."));
+	}
+
+	Ok(Options {
+		command: command,
+		input: paths[0].clone(),
+		outdir: paths.get(1).cloned(),
+		session_start: session_start,
+		bfo: bfo,
+		rgzm: rgzm,
+		driver: driver,
+		gtfs: gtfs,
+		json: json,
+		ods: ods,
+		svg: svg,
+		diagram: diagram,
+		clearance: clearance
+	})
+}
+
+fn option_value<'a, I: Iterator<Item=&'a String>>(iter: &mut I, arg: &str) -> Result<&'a String, String> {
+	match iter.next() {
+		Some(value) => Ok(value),
+		None => Err(format!("Missing value for {}.", arg))
+	}
+}
 
 fn main() {
 	std::process::exit(run());
@@ -15,33 +184,70 @@ fn main() {
 
 fn run() -> i32 {
 	let args: Vec<_> = std::env::args().collect();
-	if args.len() < 2 {
-		println!("Usage: {} <input> [<outdir>]", args[0]);
-		return 1;
+	let options = match parse_options(&args[1..]) {
+		Ok(options) => options,
+		Err(err) => {
+			println!("Error: {}", err);
+			println!("{}", USAGE.replace("{program}", &args[0]));
+			return 1;
+		}
+	};
+
+	let (xpln, report) = match load(&options) {
+		Ok(result) => result,
+		Err(code) => return code
+	};
+
+	match options.command {
+		Command::Convert => convert(&options, &xpln, &report),
+		Command::Validate => check(&xpln, &report),
+		Command::Conflicts => check_conflicts(&options, &xpln),
+		Command::Circulation => print_circulation(&xpln)
 	}
+}
 
-	let fname = PathBuf::from(&*args[1]);
-	let file = match File::open(&fname) {
+fn load(options: &Options) -> Result<(xpln::Xpln, LoadReport), i32> {
+	let fname = &options.input;
+	let file = match File::open(fname) {
 		Ok(file) => file,
-		Err(..) => { println!("Error: File {:?} not found.", fname); return 2; }
+		Err(..) => { println!("Error: File {:?} not found.", fname); return Err(2); }
 	};
 
 	println!("Loading {:?}...", fname.to_str().unwrap());
 
-	let document = ods::parse(file).unwrap();
+	let document = match fname.extension().and_then(|ext| ext.to_str()) {
+		Some("json") | Some("yaml") | Some("yml") => document::parse(file).map_err(|err| err.to_string()),
+		_ => ods::parse(file).map_err(|err| err.to_string())
+	};
+
+	let document = match document {
+		Ok(document) => document,
+		Err(err) => { println!("Error: {}", err); return Err(2); }
+	};
 
 	println!("Extracting XPLN objects...");
 
 	let mut xpln = xpln::Xpln::new();
-	xpln.load(&document);
+	xpln.session_start = options.session_start;
+
+	match xpln.load(&document) {
+		Ok(report) => Ok((xpln, report)),
+		Err(err) => { println!("Error: {}", err); Err(2) }
+	}
+}
+
+fn convert(options: &Options, xpln: &xpln::Xpln, report: &LoadReport) -> i32 {
+	for diagnostic in report.diagnostics.iter() {
+		println!("  {}", diagnostic);
+	}
 
 	//
 	// Export BFO
 	//
 
-	let outdir = match args.get(2) {
-		Some(dir) => PathBuf::from(dir),
-		None => fname.with_extension("")
+	let outdir = match options.outdir {
+		Some(ref dir) => dir.clone(),
+		None => options.input.with_extension("")
 	};
 
 	println!("Write BFOs...");
@@ -54,38 +260,164 @@ fn run() -> i32 {
 		println!("  {:?}", &path);
 
 		let mut file = File::create(&path).unwrap();
-		let mut tts  = Vec::new();
-
-		for train in xpln.trains.values() {
-			for timetable in train.timetables.iter() {
-				if timetable.station == station.name {
-					tts.push(timetable);
-				}
-			}
-		}
+		let data     = bfo::render(xpln, station, &options.bfo);
 
-		tts.sort_by(|ref t0, ref t1| t0.arrival.cmp(&t1.arrival));
+		file.write_all(data.as_bytes()).unwrap();
+	}
 
-		let mut data  = String::new();
+	//
+	// Export RgZm configuration
+	//
 
-		for timetable in tts {
-			let arrival   : &str = &timetable.arrival;
-			let departure : &str = &timetable.departure;
-			let train     : &str = &xpln.trains[&timetable.train].name();
-			let track     : &str = &timetable.track;
-			let remark    : &str = &timetable.remark;
+	if options.rgzm {
+		let path = outdir.join("rgzm.ini");
 
-			let line = format!("{arrival}\t{departure}\t{train}\t\t\t{track}\t\t\t\t\t{remark}\n",
-				arrival=arrival, departure=departure, track=track, train=train, remark=remark
-			);
+		println!("Write RgZm configuration...");
+		println!("  {:?}", &path);
 
-			data.push_str(&line);
-		}
+		let mut file = File::create(&path).unwrap();
+		let data     = rgzm::render(xpln);
+
+		file.write_all(data.as_bytes()).unwrap();
+	}
+
+	//
+	// Export GTFS feed
+	//
+
+	if options.gtfs {
+		let path = outdir.join("gtfs.zip");
+
+		println!("Write GTFS feed...");
+		println!("  {:?}", &path);
+
+		let file = File::create(&path).unwrap();
+
+		gtfs::write(xpln, &gtfs::Options::new(), file).unwrap();
+	}
+
+	//
+	// Export JSON
+	//
+
+	if options.json {
+		let path = outdir.join("xpln.json");
+
+		println!("Write JSON...");
+		println!("  {:?}", &path);
+
+		let mut file = File::create(&path).unwrap();
+		let data     = json::render(xpln, &report.diagnostics);
 
 		file.write_all(data.as_bytes()).unwrap();
 	}
 
+	//
+	// Export XPLN spreadsheet
+	//
+
+	if options.ods {
+		let path = outdir.join("xpln.ods");
+
+		println!("Write XPLN spreadsheet...");
+		println!("  {:?}", &path);
+
+		let file = File::create(&path).unwrap();
+
+		ods::write(&xpln.to_spreadsheet(), file).unwrap();
+	}
+
+	//
+	// Export time-distance graph
+	//
+
+	if options.svg {
+		let path = outdir.join("graph.svg");
+
+		println!("Write time-distance graph...");
+		println!("  {:?}", &path);
+
+		let data = match diagram::render(xpln, &options.diagram) {
+			Ok(data) => data,
+			Err(err) => { println!("Error: {}", err); return 1; }
+		};
+
+		let mut file = File::create(&path).unwrap();
+
+		file.write_all(data.as_bytes()).unwrap();
+	}
+
+	//
+	// Export driver's timetables
+	//
+
+	if let Some(format) = options.driver {
+		let dir = outdir.join("trains");
+
+		println!("Write driver's timetables...");
+
+		fs::create_dir_all(&dir).unwrap();
+
+		for train in xpln.sorted_trains() {
+			let path = dir.join(format!("{}.{}", train.number, format.extension()));
+
+			println!("  {:?}", &path);
+
+			let mut file = File::create(&path).unwrap();
+			let data     = driver::render(xpln, train, format, &options.bfo.pass_marker);
+
+			file.write_all(data.as_bytes()).unwrap();
+		}
+	}
+
+	if report.has_errors() {
+		println!("Done with {} error(s).", report.errors().len());
+		return 3;
+	}
+
 	println!("Done.");
 
 	return 0;
 }
+
+fn check(xpln: &xpln::Xpln, report: &LoadReport) -> i32 {
+	println!("Validating...");
+
+	let mut diagnostics = report.diagnostics.clone();
+	diagnostics.extend(validate::validate(xpln));
+
+	for diagnostic in diagnostics.iter() {
+		println!("  {}", diagnostic);
+	}
+
+	let errors   = diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+	let warnings = diagnostics.len() - errors;
+
+	println!("{} error(s), {} warning(s).", errors, warnings);
+
+	if errors > 0 { 3 } else { 0 }
+}
+
+fn check_conflicts(options: &Options, xpln: &xpln::Xpln) -> i32 {
+	println!("Checking track occupancy...");
+
+	let conflicts = conflicts::detect(xpln, options.clearance);
+
+	for conflict in conflicts.iter() {
+		println!("  {}", conflict);
+	}
+
+	println!("{} conflict(s).", conflicts.len());
+
+	if conflicts.is_empty() { 0 } else { 3 }
+}
+
+fn print_circulation(xpln: &xpln::Xpln) -> i32 {
+	println!("Linking trains...");
+
+	let circulations = circulation::circulations(xpln);
+
+	print!("{}", circulation::render(xpln, &circulations));
+
+	return 0;
+}
@@ -0,0 +1,4 @@
+/// Escapes `text` for XML, HTML and SVG content and attribute values.
+pub fn escape(text: &str) -> String {
+    text.replace("&", "&amp;").replace("<", "&lt;").replace(">", "&gt;").replace("\"", "&quot;")
+}
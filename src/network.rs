@@ -0,0 +1,169 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::str::FromStr;
+
+/// Header row of the StationTrack sheet as written by XPLN. Lines are kept
+/// in the last column, headed `Strecken` here.
+pub const STATION_TRACK_HEADER: [&'static str; 9] = [
+    "Name", "Enum", "PositionX/ TrackName", "PositionY/ Length", "Remark",
+    "Type", "SubType", "Owner/Hinweise", "Strecken"
+];
+
+/// One direction of a line between two adjacent stations as defined by the
+/// `Strecke` entries in StationTrack. Both directions of a line usually share
+/// the same `id`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Line {
+    pub id: u32,
+    pub from: String,
+    pub to: String,
+    /// Spreadsheet row of the definition, `0` if not loaded from a sheet.
+    pub row: usize
+}
+
+impl Line {
+    pub fn new<S: Into<String>>(id: u32, from: S, to: S) -> Line {
+        Line {
+            id: id,
+            from: from.into(),
+            to: to.into(),
+            row: 0
+        }
+    }
+
+    pub fn name(&self) -> String {
+        format!("{}-{}", self.from, self.to)
+    }
+
+    pub fn is_opposite(&self, other: &Line) -> bool {
+        self.from == other.to && self.to == other.from
+    }
+}
+
+/// Encodes lines as list entries like `/00001Strecke: LWa-Drp`, the inverse of
+/// `parse_lines`.
+pub fn encode_lines(lines: &[Line]) -> String {
+    lines.iter().map(|line| format!("/{:05}Strecke: {}-{}", line.id, line.from, line.to)).collect()
+}
+
+/// Decodes a list like `/00001Strecke: LWa-Drp/00002Strecke: Drp-LWa/...` into
+/// lines. Other entries in the list, e.g. `00021Station: LWa, Track: *`, are
+/// skipped. Returns an error naming the first malformed line entry.
+pub fn parse_lines(value: &str) -> Result<Vec<Line>, String> {
+    let mut lines = Vec::new();
+
+    for entry in value.split('/') {
+        let digits = entry.chars().take_while(|c| c.is_digit(10)).count();
+        let text   = &entry[digits..];
+
+        if !text.starts_with("Strecke:") { continue }
+
+        let id = match u32::from_str(&entry[..digits]) {
+            Ok(id) => id,
+            Err(_) => return Err(format!("Invalid line id: {}", entry))
+        };
+
+        let stations : Vec<&str> = text["Strecke:".len()..].trim().splitn(2, '-').collect();
+
+        if stations.len() != 2 || stations[0].is_empty() || stations[1].is_empty() {
+            return Err(format!("Invalid line: {}", entry));
+        }
+
+        lines.push(Line::new(id, stations[0], stations[1]));
+    }
+
+    Ok(lines)
+}
+
+/// Undirected graph of stations connected by lines.
+#[derive(Debug)]
+pub struct StationGraph {
+    edges: BTreeMap<String, BTreeSet<String>>
+}
+
+impl StationGraph {
+    pub fn new(lines: &[Line]) -> StationGraph {
+        let mut edges = BTreeMap::new();
+
+        for line in lines.iter() {
+            edges.entry(line.from.clone()).or_insert(BTreeSet::new()).insert(line.to.clone());
+            edges.entry(line.to.clone()).or_insert(BTreeSet::new()).insert(line.from.clone());
+        }
+
+        StationGraph { edges: edges }
+    }
+
+    /// Names of all stations connected to at least one line, sorted.
+    pub fn stations(&self) -> Vec<&str> {
+        self.edges.keys().map(|name| &name[..]).collect()
+    }
+
+    /// Names of the stations adjacent to `station`, sorted.
+    pub fn neighbours(&self, station: &str) -> Vec<&str> {
+        match self.edges.get(station) {
+            Some(neighbours) => neighbours.iter().map(|name| &name[..]).collect(),
+            None => Vec::new()
+        }
+    }
+
+    pub fn is_adjacent(&self, from: &str, to: &str) -> bool {
+        self.edges.get(from).map(|n| n.contains(to)).unwrap_or(false)
+    }
+
+    /// Shortest sequence of stations from `from` to `to`, both included.
+    pub fn path(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        if !self.edges.contains_key(from) || !self.edges.contains_key(to) {
+            return None;
+        }
+
+        let mut previous = BTreeMap::new();
+        let mut queue    = VecDeque::new();
+
+        previous.insert(from, from);
+        queue.push_back(from);
+
+        while let Some(station) = queue.pop_front() {
+            if station == to {
+                let mut path = vec![String::from(to)];
+                let mut current = to;
+
+                while current != from {
+                    current = previous[current];
+                    path.push(String::from(current));
+                }
+
+                path.reverse();
+                return Some(path);
+            }
+
+            for neighbour in self.neighbours(station) {
+                if !previous.contains_key(neighbour) {
+                    previous.insert(neighbour, station);
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_lines, Line};
+
+    #[test]
+    fn parse_lines_skips_other_entries() {
+        let lines = parse_lines("/00001Strecke: LWa-Drp/00021Station: LWa, Track: */00002Strecke: Drp-LWa").unwrap();
+
+        assert_eq!(vec![Line::new(1, "LWa", "Drp"), Line::new(2, "Drp", "LWa")], lines);
+        assert_eq!(Vec::<Line>::new(), parse_lines("").unwrap());
+    }
+
+    #[test]
+    fn parse_lines_rejects_malformed_entries() {
+        assert!(parse_lines("/Strecke: LWa-Drp").is_err());
+        assert!(parse_lines("/00001Strecke: LWa").is_err());
+        assert!(parse_lines("/00001Strecke: LWa-").is_err());
+        assert!(parse_lines("/00001Strecke: -Drp").is_err());
+    }
+}
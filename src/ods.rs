@@ -1,10 +1,12 @@
 use std::io;
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 use std::convert;
 use std::mem;
+use std::cmp;
 use std::str::FromStr;
 use std::error::Error as _StdError;
 
+use zip::{CompressionMethod, ZipWriter};
 use zip::read::ZipArchive;
 use zip::result::ZipError;
 
@@ -13,6 +15,15 @@ use xml::reader::EventReader;
 use xml::reader::events::*;
 use xml::attribute::OwnedAttribute;
 
+use markup::escape;
+
+const OFFICE_NS: &'static str = "urn:oasis:names:tc:opendocument:xmlns:office:1.0";
+
+const MIMETYPE: &'static str = "application/vnd.oasis.opendocument.spreadsheet";
+
+/// Maximum number of copies created for a row with `table:number-rows-repeated`.
+const MAX_REPEATED_ROWS: usize = 1000;
+
 #[derive(Debug)]
 pub struct Error {
     description: String,
@@ -24,6 +35,27 @@ impl Error {
     }
 }
 
+impl ::std::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(fmt, "{}", self.description)
+    }
+}
+
+impl ::std::error::Error for Error {
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn cause(&self) -> Option<&::std::error::Error> {
+        match self.cause {
+            Some(ErrorCause::Io(ref err)) => Some(err),
+            Some(ErrorCause::Zip(ref err)) => Some(err),
+            Some(ErrorCause::Xml(ref err)) => Some(err),
+            None => None
+        }
+    }
+}
+
 impl convert::From<ZipError> for Error {
     fn from(err: ZipError) -> Error {
         Error::new(
@@ -76,10 +108,18 @@ pub struct Spreadsheet {
 }
 
 impl Spreadsheet {
-    fn new() -> Spreadsheet {
+    pub fn new() -> Spreadsheet {
         return Spreadsheet { tables: Vec::new() };
     }
 
+    pub fn push(&mut self, table: Table) {
+        self.tables.push(table);
+    }
+
+    pub fn tables(&self) -> &Vec<Table> {
+        return &self.tables;
+    }
+
     pub fn get(&self, table_name: &str) -> Option<&Table> {
         for i in 0..self.tables.len() {
             if self.tables[i].name == table_name {
@@ -97,6 +137,20 @@ pub struct Table {
 }
 
 impl Table {
+    pub fn new<S: Into<String>>(name: S) -> Table {
+        Table { name: name.into(), rows: Vec::new() }
+    }
+
+    /// Appends a row built from `cells`, numbered after the last row.
+    pub fn push(&mut self, cells: Vec<Cell>) {
+        let number = self.rows.len() + 1;
+        self.rows.push(Row::new(number, cells));
+    }
+
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+
     pub fn rows(&self) -> &Vec<Row> {
         return &self.rows;
     }
@@ -147,10 +201,32 @@ impl ::std::fmt::Display for Table {
 }
 
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Row {
+    /// 1-based row number as shown in the spreadsheet.
     pub number: usize,
-    pub values: Vec<String>
+    pub values: Vec<String>,
+    pub cells: Vec<Cell>
+}
+
+impl Row {
+    /// Builds a row from typed cells with their display text as values.
+    pub fn new(number: usize, cells: Vec<Cell>) -> Row {
+        Row {
+            number: number,
+            values: cells.iter().map(|cell| cell.to_string()).collect(),
+            cells: cells
+        }
+    }
+
+    /// Returns the typed value of the cell at `index` or `Cell::Empty` if the
+    /// row is shorter.
+    pub fn cell(&self, index: usize) -> Cell {
+        match self.cells.get(index) {
+            Some(cell) => cell.clone(),
+            None => Cell::Empty
+        }
+    }
 }
 
 impl ::std::fmt::Display for Row {
@@ -161,17 +237,151 @@ impl ::std::fmt::Display for Row {
     }
 }
 
+/// Typed cell value as given by `office:value-type` and the matching value
+/// attribute. The display text is kept separately in `Row::values`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Cell {
+    Empty,
+    String(String),
+    Float(f64),
+    Percentage(f64),
+    Currency(f64, String),
+    Date(String),
+    /// Duration in seconds, e.g. `PT04H51M00S` is `17460`.
+    Time(i64),
+    Boolean(bool)
+}
+
+impl Cell {
+    pub fn is_empty(&self) -> bool {
+        match *self {
+            Cell::Empty => true,
+            Cell::String(ref value) => value.is_empty(),
+            _ => false
+        }
+    }
+
+    /// Returns the numeric value of the cell. String cells are accepted if
+    /// they contain a plain number, with `.` or `,` as decimal separator.
+    pub fn as_float(&self) -> Option<f64> {
+        match *self {
+            Cell::Float(value) |
+            Cell::Percentage(value) |
+            Cell::Currency(value, _) => Some(value),
+            Cell::String(ref value) => f64::from_str(&value.trim().replace(",", ".")).ok(),
+            _ => None
+        }
+    }
+
+    /// Returns the cell as a non-negative integer. Float cells must not have
+    /// a fractional part; string cells are accepted if they contain a plain
+    /// number.
+    pub fn as_usize(&self) -> Option<usize> {
+        match *self {
+            Cell::Float(value) => {
+                if value >= 0.0 && value.fract() == 0.0 {
+                    Some(value as usize)
+                } else {
+                    None
+                }
+            },
+            Cell::String(ref value) => usize::from_str(value.trim()).ok(),
+            _ => None
+        }
+    }
+
+    pub fn as_time(&self) -> Option<i64> {
+        match *self {
+            Cell::Time(value) => Some(value),
+            _ => None
+        }
+    }
+
+    fn parse(attributes: &Vec<OwnedAttribute>) -> Result<Cell, Error> {
+        let value_type = extract_office_attribute(attributes, "value-type").unwrap_or(String::new());
+
+        let cell = match value_type.as_ref() {
+            "string" => Cell::String(
+                extract_office_attribute(attributes, "string-value").unwrap_or(String::new())
+            ),
+            "float" => Cell::Float(try!(parse_float(attributes, "value"))),
+            "percentage" => Cell::Percentage(try!(parse_float(attributes, "value"))),
+            "currency" => Cell::Currency(
+                try!(parse_float(attributes, "value")),
+                extract_office_attribute(attributes, "currency").unwrap_or(String::new())
+            ),
+            "date" => match extract_office_attribute(attributes, "date-value") {
+                Some(value) => Cell::Date(value),
+                None => return Err(Error::from("Parser error on office:date-value: Missing value."))
+            },
+            "time" => match extract_office_attribute(attributes, "time-value") {
+                Some(value) => match parse_duration(&value) {
+                    Some(value) => Cell::Time(value),
+                    None => {
+                        return Err(Error::from(format!(
+                            "Parser error on office:time-value: Not a valid duration: {}", value
+                        )));
+                    }
+                },
+                None => return Err(Error::from("Parser error on office:time-value: Missing value."))
+            },
+            "boolean" => match extract_office_attribute(attributes, "boolean-value") {
+                Some(ref value) if value == "true" => Cell::Boolean(true),
+                Some(ref value) if value == "false" => Cell::Boolean(false),
+                _ => return Err(Error::from("Parser error on office:boolean-value: Not a valid boolean."))
+            },
+            // Missing and unknown types, e.g. `void`, are kept as the cell's text
+            _ => Cell::String(String::new())
+        };
+
+        Ok(cell)
+    }
+}
+
+/// Display text of the cell. Times are written as `HH:MM`.
+impl ::std::fmt::Display for Cell {
+    fn fmt(&self, fmt: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        match *self {
+            Cell::Empty => Ok(()),
+            Cell::String(ref value) | Cell::Date(ref value) => write!(fmt, "{}", value),
+            Cell::Float(value) => write!(fmt, "{}", value),
+            Cell::Percentage(value) => write!(fmt, "{}%", value * 100.0),
+            Cell::Currency(value, ref currency) => write!(fmt, "{} {}", value, currency),
+            Cell::Time(value) => write!(fmt, "{:02}:{:02}", value / 3600, value % 3600 / 60),
+            Cell::Boolean(value) => write!(fmt, "{}", if value { "TRUE" } else { "FALSE" })
+        }
+    }
+}
+
 #[derive(PartialEq, Debug)]
 enum Token {
     Bottom,
     Table,
-    Row,
+    Row {
+        number_rows_repeated: usize
+    },
     Cell {
-        number_columns_repeated: usize
+        number_columns_repeated: usize,
+        cell: Cell
     }
 }
 
-pub fn parse<T: Read+Seek>(file: T) -> Result<Spreadsheet, Error> {
+/// Reads an OpenDocument spreadsheet, either a zip archive (`.ods`) or a
+/// flat XML document (`.fods`), distinguished by the zip signature.
+pub fn parse<T: Read+Seek>(mut file: T) -> Result<Spreadsheet, Error> {
+    let mut signature = [0u8; 2];
+    let length = try!(file.read(&mut signature));
+
+    try!(file.seek(io::SeekFrom::Start(0)));
+
+    if length == 2 && &signature == b"PK" {
+        parse_archive(file)
+    } else {
+        parse_content(file)
+    }
+}
+
+fn parse_archive<T: Read+Seek>(file: T) -> Result<Spreadsheet, Error> {
     let mut archive = try!(ZipArchive::new(file));
 
     {
@@ -180,19 +390,27 @@ pub fn parse<T: Read+Seek>(file: T) -> Result<Spreadsheet, Error> {
 
         try!(file.read_to_string(&mut mime));
 
-        if mime != "application/vnd.oasis.opendocument.spreadsheet" {
+        if mime != MIMETYPE {
             return Err(Error::from(format!("Invalid mimetype: {}", mime)));
         }
     }
 
-    let file  = try!(archive.by_name("content.xml"));
+    let file = try!(archive.by_name("content.xml"));
 
+    parse_content(file)
+}
+
+/// Reads the tables from `content.xml` of a spreadsheet archive or from a
+/// flat `office:document`, whose `office:mimetype` must be a spreadsheet.
+fn parse_content<T: Read>(file: T) -> Result<Spreadsheet, Error> {
     let mut stack       = Vec::new();
     let mut parser      = EventReader::new(file);
 
     let mut value       = String::new();
     let mut values      = Vec::new();
+    let mut cells       = Vec::new();
     let mut rows        = Vec::new();
+    let mut number      = 1;
     let mut table       = None;
     let mut spreadsheet = Spreadsheet::new();
 
@@ -202,6 +420,14 @@ pub fn parse<T: Read+Seek>(file: T) -> Result<Spreadsheet, Error> {
         match event {
             XmlEvent::StartElement { name, attributes, namespace: _ } => {
                 match name.local_name.as_ref() {
+                    "document" => {
+                        match extract_office_attribute(&attributes, "mimetype") {
+                            Some(ref mime) if mime != MIMETYPE => {
+                                return Err(Error::from(format!("Invalid mimetype: {}", mime)));
+                            },
+                            _ => ()
+                        }
+                    },
                     "table" => {
                         assert_eq!(Token::Bottom, *stack.last().unwrap());
 
@@ -216,25 +442,26 @@ pub fn parse<T: Read+Seek>(file: T) -> Result<Spreadsheet, Error> {
                     },
                     "table-row" => {
                         assert_eq!(Token::Table, *stack.last().unwrap());
-                        stack.push(Token::Row);
+
+                        let number_rows_repeated = try!(parse_repeated(&attributes, "number-rows-repeated"));
+
+                        stack.push(Token::Row {
+                            number_rows_repeated: number_rows_repeated
+                        });
                     },
                     "table-cell" => {
-                        assert_eq!(Token::Row, *stack.last().unwrap());
-
-                        let number_columns_repeated = match extract_attribute(&attributes, "number-columns-repeated") {
-                            Some(value) => match usize::from_str(value.as_ref()) {
-                                Ok(value) => value,
-                                Err(_) => {
-                                    return Err(Error::from(
-                                        "Parser error on table:number-columns-repeated: Not a valid number."
-                                    ));
-                                }
-                            },
-                            None => 1
-                        };
+                        match *stack.last().unwrap() {
+                            Token::Row{..} => (),
+                            _ => { panic!("Invalid ODS parser state"); }
+                        }
+
+                        let number_columns_repeated = try!(parse_repeated(&attributes, "number-columns-repeated"));
+
+                        let cell = try!(Cell::parse(&attributes));
 
                         stack.push(Token::Cell {
-                            number_columns_repeated: number_columns_repeated
+                            number_columns_repeated: number_columns_repeated,
+                            cell: cell
                         });
                     },
                     _ => ()
@@ -248,6 +475,8 @@ pub fn parse<T: Read+Seek>(file: T) -> Result<Spreadsheet, Error> {
                         let name = mem::replace(&mut table, None).unwrap();
                         let rvec = mem::replace(&mut rows, Vec::new());
 
+                        number = 1;
+
                         let table = Table {
                             name: name,
                             rows: rvec
@@ -256,25 +485,52 @@ pub fn parse<T: Read+Seek>(file: T) -> Result<Spreadsheet, Error> {
                         spreadsheet.tables.push(table);
                     },
                     "table-row" => {
-                        assert_eq!(Token::Row, stack.pop().unwrap());
+                        let number_rows_repeated = match stack.pop() {
+                            Some(Token::Row { number_rows_repeated }) => number_rows_repeated,
+                            _ => { panic!("Invalid ODS parser state"); }
+                        };
 
-                        let     index = rows.len();
                         let mut vvec  = mem::replace(&mut values, Vec::new());
+                        let mut cvec  = mem::replace(&mut cells, Vec::new());
                         let     last  = vvec.iter().rposition(|&ref v : &String| v.len() > 0usize).unwrap_or(0);
 
                         vvec.truncate(last + 1);
+                        cvec.truncate(last + 1);
+
+                        let row = Row { number: number, values: vvec, cells: cvec };
+
+                        // LibreOffice pads tables with up to a million repeated
+                        // empty rows. Only expand a limited number of them but
+                        // keep counting so row numbers match the sheet. Rows
+                        // with content are never dropped.
+                        if number_rows_repeated > MAX_REPEATED_ROWS && row.values.iter().any(|v| !v.is_empty()) {
+                            return Err(Error::from(format!(
+                                "Parser error on table:number-rows-repeated: Row {} of {} with content repeated {} times, at most {} supported.",
+                                number, table.clone().unwrap_or(String::new()), number_rows_repeated, MAX_REPEATED_ROWS
+                            )));
+                        }
 
-                        rows.push(Row { number: index, values: vvec });
+                        for index in 0..cmp::min(number_rows_repeated, MAX_REPEATED_ROWS) {
+                            rows.push(Row { number: number + index, .. row.clone() });
+                        }
+
+                        number += number_rows_repeated;
                     },
                     "table-cell" => {
                         match stack.pop() {
                             Some(Token::Cell {
-                                number_columns_repeated
+                                number_columns_repeated,
+                                cell
                             }) => {
-                                let val = mem::replace(&mut value, String::new());
+                                let val  = mem::replace(&mut value, String::new());
+                                let cell = match cell {
+                                    Cell::String(ref string) if string.is_empty() => Cell::String(val.clone()),
+                                    cell => cell
+                                };
 
                                 for _ in 0..number_columns_repeated {
                                     values.push(String::from(val.as_ref()));
+                                    cells.push(cell.clone());
                                 }
                             },
                             _ => { panic!("Invalid ODS parser state"); }
@@ -298,6 +554,164 @@ pub fn parse<T: Read+Seek>(file: T) -> Result<Spreadsheet, Error> {
     return Ok(spreadsheet);
 }
 
+/// Writes `spreadsheet` as OpenDocument spreadsheet: the uncompressed
+/// `mimetype` first, followed by the manifest and `content.xml` with one
+/// table per sheet. Cells keep their type; no styles are written.
+pub fn write<T: Write+Seek>(spreadsheet: &Spreadsheet, file: T) -> Result<T, Error> {
+    let mut archive = ZipWriter::new(file);
+
+    try!(archive.start_file("mimetype", CompressionMethod::Stored));
+    try!(archive.write_all(MIMETYPE.as_bytes()));
+
+    try!(archive.start_file("META-INF/manifest.xml", CompressionMethod::Deflated));
+    try!(archive.write_all(format!(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<manifest:manifest xmlns:manifest=\"urn:oasis:names:tc:opendocument:xmlns:manifest:1.0\" manifest:version=\"1.2\">\n",
+        " <manifest:file-entry manifest:full-path=\"/\" manifest:version=\"1.2\" manifest:media-type=\"{}\"/>\n",
+        " <manifest:file-entry manifest:full-path=\"content.xml\" manifest:media-type=\"text/xml\"/>\n",
+        "</manifest:manifest>\n"
+    ), MIMETYPE).as_bytes()));
+
+    try!(archive.start_file("content.xml", CompressionMethod::Deflated));
+    try!(archive.write_all(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<office:document-content",
+        " xmlns:office=\"urn:oasis:names:tc:opendocument:xmlns:office:1.0\"",
+        " xmlns:table=\"urn:oasis:names:tc:opendocument:xmlns:table:1.0\"",
+        " xmlns:text=\"urn:oasis:names:tc:opendocument:xmlns:text:1.0\"",
+        " office:version=\"1.2\">\n",
+        "<office:body>\n",
+        "<office:spreadsheet>\n"
+    ).as_bytes()));
+
+    for table in spreadsheet.tables.iter() {
+        try!(write!(archive, "<table:table table:name=\"{}\">\n", escape(&table.name)));
+
+        for row in table.rows.iter() {
+            try!(archive.write_all(b"<table:table-row>"));
+
+            for (index, cell) in row.cells.iter().enumerate() {
+                let text = row.values.get(index).cloned().unwrap_or(cell.to_string());
+                try!(write_cell(&mut archive, cell, &text));
+            }
+
+            try!(archive.write_all(b"</table:table-row>\n"));
+        }
+
+        try!(archive.write_all(b"</table:table>\n"));
+    }
+
+    try!(archive.write_all(b"</office:spreadsheet>\n</office:body>\n</office:document-content>\n"));
+
+    Ok(try!(archive.finish()))
+}
+
+fn write_cell<T: Write>(file: &mut T, cell: &Cell, text: &str) -> io::Result<()> {
+    let attributes = match *cell {
+        Cell::Empty => return file.write_all(b"<table:table-cell/>"),
+        Cell::String(_) => String::from("office:value-type=\"string\""),
+        Cell::Float(value) => format!("office:value-type=\"float\" office:value=\"{}\"", value),
+        Cell::Percentage(value) => format!("office:value-type=\"percentage\" office:value=\"{}\"", value),
+        Cell::Currency(value, ref currency) => format!(
+            "office:value-type=\"currency\" office:value=\"{}\" office:currency=\"{}\"", value, escape(currency)
+        ),
+        Cell::Date(ref value) => format!("office:value-type=\"date\" office:date-value=\"{}\"", escape(value)),
+        Cell::Time(value) => format!("office:value-type=\"time\" office:time-value=\"{}\"", format_duration(value)),
+        Cell::Boolean(value) => format!("office:value-type=\"boolean\" office:boolean-value=\"{}\"", value)
+    };
+
+    write!(file, "<table:table-cell {}><text:p>{}</text:p></table:table-cell>", attributes, escape(text))
+}
+
+/// Formats seconds as ISO 8601 duration, e.g. `17460` as `PT04H51M00S`.
+fn format_duration(seconds: i64) -> String {
+    let sign    = if seconds < 0 { "-" } else { "" };
+    let seconds = seconds.abs();
+
+    format!("{}PT{:02}H{:02}M{:02}S", sign, seconds / 3600, seconds % 3600 / 60, seconds % 60)
+}
+
+fn parse_repeated(attributes: &Vec<OwnedAttribute>, name: &str) -> Result<usize, Error> {
+    match extract_attribute(attributes, name) {
+        Some(value) => match usize::from_str(value.as_ref()) {
+            Ok(value) => Ok(value),
+            Err(_) => Err(Error::from(format!("Parser error on table:{}: Not a valid number.", name)))
+        },
+        None => Ok(1)
+    }
+}
+
+/// Like `extract_attribute` but only matches attributes in the `office`
+/// namespace, as e.g. LibreOffice adds its own `calcext:value-type`.
+fn extract_office_attribute(attributes: &Vec<OwnedAttribute>, name: &str) -> Option<String> {
+    for attr in attributes.iter() {
+        if attr.name.local_name == name && attr.name.namespace.as_ref().map(|ns| ns == OFFICE_NS).unwrap_or(false) {
+            return Some(attr.value.clone());
+        }
+    }
+
+    None
+}
+
+fn parse_float(attributes: &Vec<OwnedAttribute>, name: &str) -> Result<f64, Error> {
+    match extract_office_attribute(attributes, name) {
+        Some(value) => match f64::from_str(value.as_ref()) {
+            Ok(value) => Ok(value),
+            Err(_) => Err(Error::from(format!("Parser error on office:{}: Not a valid number.", name)))
+        },
+        None => Err(Error::from(format!("Parser error on office:{}: Missing value.", name)))
+    }
+}
+
+/// Parses an ISO 8601 duration as used for `office:time-value` (e.g.
+/// `PT04H51M00S` or `-P1DT2H`) into seconds. Fractional seconds are rounded.
+fn parse_duration(value: &str) -> Option<i64> {
+    let (negative, value) = if value.starts_with('-') {
+        (true, &value[1..])
+    } else {
+        (false, value)
+    };
+
+    if !value.starts_with('P') {
+        return None;
+    }
+
+    let mut seconds = 0f64;
+    let mut number  = String::new();
+    let mut time    = false;
+
+    for c in value[1..].chars() {
+        match c {
+            '0'...'9' | '.' | ',' => number.push(if c == ',' { '.' } else { c }),
+            'T' if !time && number.is_empty() => time = true,
+            _ => {
+                let factor = match (c, time) {
+                    ('D', false) => 86400f64,
+                    ('H', true)  => 3600f64,
+                    ('M', true)  => 60f64,
+                    ('S', true)  => 1f64,
+                    _ => return None
+                };
+
+                match f64::from_str(number.as_ref()) {
+                    Ok(n) => seconds += n * factor,
+                    Err(_) => return None
+                }
+
+                number.clear();
+            }
+        }
+    }
+
+    if !number.is_empty() {
+        return None;
+    }
+
+    let seconds = seconds.round() as i64;
+
+    Some(if negative { -seconds } else { seconds })
+}
+
 fn extract_attribute(attributes: &Vec<OwnedAttribute>, name: &str) -> Option<String> {
     for attr in attributes.iter() {
         if attr.name.local_name == name {
@@ -310,3 +724,156 @@ fn extract_attribute(attributes: &Vec<OwnedAttribute>, name: &str) -> Option<Str
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::{CompressionMethod, ZipWriter};
+
+    use super::{format_duration, parse, parse_duration, Cell, MAX_REPEATED_ROWS, MIMETYPE, Spreadsheet};
+
+    /// Minimal flat document with one table `T` holding `rows`.
+    fn document(rows: &str) -> String {
+        format!(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>",
+            "<office:document xmlns:office=\"urn:oasis:names:tc:opendocument:xmlns:office:1.0\" ",
+            "xmlns:table=\"urn:oasis:names:tc:opendocument:xmlns:table:1.0\" ",
+            "xmlns:text=\"urn:oasis:names:tc:opendocument:xmlns:text:1.0\" office:mimetype=\"{}\">",
+            "<office:body><office:spreadsheet><table:table table:name=\"T\">{}</table:table>",
+            "</office:spreadsheet></office:body></office:document>"
+        ), MIMETYPE, rows)
+    }
+
+    /// Archive with `mimetype` and `rows` packed as `content.xml`.
+    fn archive(mimetype: &str, rows: &str) -> Cursor<Vec<u8>> {
+        let content = document(rows)
+            .replace(&format!(" office:mimetype=\"{}\"", MIMETYPE), "")
+            .replace("office:document ", "office:document-content ")
+            .replace("</office:document>", "</office:document-content>");
+
+        let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+
+        archive.start_file("mimetype", CompressionMethod::Stored).unwrap();
+        archive.write_all(mimetype.as_bytes()).unwrap();
+        archive.start_file("content.xml", CompressionMethod::Deflated).unwrap();
+        archive.write_all(content.as_bytes()).unwrap();
+
+        let mut file = archive.finish().unwrap();
+
+        file.set_position(0);
+        file
+    }
+
+    /// Number, values and cells of a row.
+    type Contents = (usize, Vec<String>, Vec<Cell>);
+
+    /// Table names with the contents of each row.
+    fn contents(spreadsheet: &Spreadsheet) -> Vec<(String, Vec<Contents>)> {
+        spreadsheet.tables().iter().map(|table| {
+            (String::from(table.name()), table.rows().iter().map(|row| (row.number, row.values.clone(), row.cells.clone())).collect())
+        }).collect()
+    }
+
+    #[test]
+    fn parse_reads_text_of_untyped_cells() {
+        let spreadsheet = parse(Cursor::new(document(concat!(
+            "<table:table-row><table:table-cell><text:p>5:10</text:p></table:table-cell>",
+            "<table:table-cell office:value-type=\"void\"><text:p>FD 61</text:p></table:table-cell>",
+            "<table:table-cell/><table:table-cell><text:p>x</text:p></table:table-cell></table:table-row>"
+        )))).unwrap();
+
+        let row = &spreadsheet.tables()[0].rows()[0];
+
+        assert_eq!(Cell::String(String::from("5:10")), row.cell(0));
+        assert_eq!(Cell::String(String::from("FD 61")), row.cell(1));
+        assert!(row.cell(2).is_empty());
+        assert_eq!(vec!["5:10", "FD 61", "", "x"], row.values);
+    }
+
+    /// Row with a single text cell, repeated `repeated` times.
+    fn row(text: &str, repeated: usize) -> String {
+        format!(
+            "<table:table-row table:number-rows-repeated=\"{}\"><table:table-cell><text:p>{}</text:p></table:table-cell></table:table-row>",
+            repeated, text
+        )
+    }
+
+    #[test]
+    fn parse_expands_repeated_rows() {
+        let spreadsheet = parse(Cursor::new(document(&(row("a", 1) + &row("b", 3) + &row("c", 1))))).unwrap();
+        let rows = spreadsheet.tables()[0].rows();
+
+        assert_eq!(vec![1, 2, 3, 4, 5], rows.iter().map(|row| row.number).collect::<Vec<_>>());
+        assert_eq!(vec!["a", "b", "b", "b", "c"], rows.iter().map(|row| row.values[0].as_ref()).collect::<Vec<&str>>());
+    }
+
+    #[test]
+    fn parse_caps_repeated_empty_rows() {
+        let spreadsheet = parse(Cursor::new(document(&(row("a", 1) + &row("", 2000) + &row("c", 1))))).unwrap();
+        let rows = spreadsheet.tables()[0].rows();
+
+        assert_eq!(1 + MAX_REPEATED_ROWS + 1, rows.len());
+        assert_eq!(MAX_REPEATED_ROWS + 1, rows[MAX_REPEATED_ROWS].number);
+
+        // The row after the run keeps its number in the sheet
+        let last = rows.last().unwrap();
+
+        assert_eq!(2002, last.number);
+        assert_eq!(vec!["c"], last.values);
+    }
+
+    #[test]
+    fn parse_rejects_repeated_rows_with_content() {
+        assert!(parse(Cursor::new(document(&row("a", MAX_REPEATED_ROWS)))).is_ok());
+
+        let error = parse(Cursor::new(document(&row("a", MAX_REPEATED_ROWS + 1)))).unwrap_err();
+
+        assert!(error.to_string().contains("Row 1 of T with content repeated 1001 times"));
+    }
+
+    #[test]
+    fn parse_reads_flat_and_zipped_documents_alike() {
+        let rows = concat!(
+            "<table:table-row><table:table-cell office:value-type=\"string\"><text:p>Bf</text:p></table:table-cell>",
+            "<table:table-cell office:value-type=\"float\" office:value=\"2\"><text:p>2</text:p></table:table-cell></table:table-row>",
+            "<table:table-row table:number-rows-repeated=\"2\"/>",
+            "<table:table-row><table:table-cell office:value-type=\"time\" office:time-value=\"PT05H10M00S\"><text:p>05:10</text:p></table:table-cell></table:table-row>"
+        );
+
+        let flat = parse(Cursor::new(document(rows))).unwrap();
+        let zipped = parse(archive(MIMETYPE, rows)).unwrap();
+
+        assert_eq!("T", flat.tables()[0].name());
+        assert_eq!(vec![1, 2, 3, 4], flat.tables()[0].rows().iter().map(|row| row.number).collect::<Vec<_>>());
+        assert_eq!(contents(&zipped), contents(&flat));
+    }
+
+    #[test]
+    fn parse_rejects_other_mimetypes() {
+        let text = "application/vnd.oasis.opendocument.text";
+        let flat = document("").replace(MIMETYPE, text);
+
+        assert!(parse(Cursor::new(flat)).unwrap_err().to_string().contains("Invalid mimetype"));
+        assert!(parse(archive(text, "")).unwrap_err().to_string().contains("Invalid mimetype"));
+    }
+
+    #[test]
+    fn parse_duration_reads_iso_durations() {
+        assert_eq!(Some(5 * 3600 + 11 * 60), parse_duration("PT05H11M00S"));
+        assert_eq!(Some(86400 + 1800), parse_duration("P1DT00H30M00S"));
+        assert_eq!(Some(-90), parse_duration("-PT00H01M30S"));
+        assert_eq!(Some(90), parse_duration("PT00H01M29,6S"));
+        assert_eq!(None, parse_duration("05:11"));
+        assert_eq!(None, parse_duration("PT05X"));
+    }
+
+    #[test]
+    fn format_duration_round_trip() {
+        for &seconds in [0, 59, 3600, 5 * 3600 + 11 * 60, 25 * 3600 + 30 * 60 + 15, -90].iter() {
+            assert_eq!(Some(seconds), parse_duration(&format_duration(seconds)));
+        }
+
+        assert_eq!("PT25H30M15S", format_duration(25 * 3600 + 30 * 60 + 15));
+    }
+}
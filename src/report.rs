@@ -0,0 +1,121 @@
+use std::fmt::{Display, Formatter, Error};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Severity {
+    Warning,
+    Error
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error")
+        }
+    }
+}
+
+/// A problem found in the spreadsheet, located by sheet name, 1-based row
+/// and column number where known.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub sheet: String,
+    pub row: Option<usize>,
+    pub column: Option<usize>,
+    /// Machine-readable identifier, e.g. `invalid-train-number`.
+    pub code: &'static str,
+    pub message: String
+}
+
+impl Diagnostic {
+    pub fn new<S: Into<String>>(severity: Severity, code: &'static str, message: S) -> Diagnostic {
+        Diagnostic {
+            severity: severity,
+            sheet: String::new(),
+            row: None,
+            column: None,
+            code: code,
+            message: message.into()
+        }
+    }
+
+    pub fn at<S: Into<String>>(mut self, sheet: S, row: usize, column: Option<usize>) -> Diagnostic {
+        self.sheet  = sheet.into();
+        self.row    = Some(row);
+        self.column = column;
+        self
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        try!(write!(f, "{}", self.severity));
+
+        if !self.sheet.is_empty() {
+            try!(write!(f, ": {}", self.sheet));
+        }
+
+        if let Some(row) = self.row {
+            try!(write!(f, " row {}", row));
+        }
+
+        if let Some(column) = self.column {
+            try!(write!(f, " column {}", column));
+        }
+
+        try!(write!(f, ": {} [{}]", self.message, self.code));
+
+        Ok(())
+    }
+}
+
+/// Diagnostics collected while loading a spreadsheet.
+#[derive(Debug)]
+pub struct LoadReport {
+    pub diagnostics: Vec<Diagnostic>
+}
+
+impl LoadReport {
+    pub fn new() -> LoadReport {
+        LoadReport { diagnostics: Vec::new() }
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.severity == Severity::Error)
+    }
+
+    pub fn errors(&self) -> Vec<&Diagnostic> {
+        self.diagnostics.iter().filter(|d| d.severity == Severity::Error).collect()
+    }
+
+    pub fn warnings(&self) -> Vec<&Diagnostic> {
+        self.diagnostics.iter().filter(|d| d.severity == Severity::Warning).collect()
+    }
+}
+
+/// Problems that prevent loading a spreadsheet at all.
+#[derive(Debug)]
+pub enum LoadError {
+    MissingTable(String)
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            LoadError::MissingTable(ref name) => write!(f, "Missing {} table.", name)
+        }
+    }
+}
+
+impl ::std::error::Error for LoadError {
+    fn description(&self) -> &str {
+        match *self {
+            LoadError::MissingTable(..) => "Missing table"
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! RgZm layout configuration.
+//!
+//! The file layout is not taken from an RgZm specification but defined by
+//! this module as INI-style sections:
+//!
+//! ```text
+//! [Stations]
+//! Count=<n>
+//! Station<i>=<code>
+//!
+//! [Station <code>]
+//! Name=<full name>
+//! Position=<x>;<y>
+//! Tracks=<n>
+//! Track<i>=<name>;<kind>;<notes>;<length>;<flags>
+//!
+//! [Routes]
+//! Count=<n>
+//! Route<i>=<from>;<to>[;<line id>]
+//! ```
+//!
+//! `Position` is left out for stations without one. Flags are `P` for tracks
+//! with a platform and `D` for through tracks. In all values `\`, `;`, `[`
+//! and `]` are escaped with a backslash and line breaks are written as `\n`
+//! and `\r`, so every entry stays on one line and splits into its fields at
+//! unescaped `;` only.
+
+use xpln::Xpln;
+
+/// Renders the RgZm layout configuration: one section per station with its
+/// tracks and a route section listing the lines, or the station connections
+/// used by the timetable if the sheet defines no lines.
+pub fn render(xpln: &Xpln) -> String {
+    let mut data = String::new();
+
+    let stations = xpln.sorted_stations();
+
+    data.push_str("[Stations]\n");
+    data.push_str(&format!("Count={}\n", stations.len()));
+
+    for (index, station) in stations.iter().enumerate() {
+        data.push_str(&format!("Station{}={}\n", index + 1, escape(&station.name)));
+    }
+
+    for station in stations.iter() {
+        data.push_str("\n");
+        data.push_str(&format!("[Station {}]\n", escape(&station.name)));
+        data.push_str(&format!("Name={}\n", escape(&station.remark)));
+
+        if let Some(position) = station.position {
+            data.push_str(&format!("Position={};{}\n", position.x, position.y));
+        }
+
+        data.push_str(&format!("Tracks={}\n", station.tracks.len()));
+
+        for (index, track) in station.tracks.iter().enumerate() {
+            let length = track.length.map(|l| l.to_string()).unwrap_or(String::new());
+            let flags  = format!("{}{}",
+                if track.platform { "P" } else { "" },
+                if track.through { "D" } else { "" }
+            );
+
+            data.push_str(&format!("Track{}={};{};{};{};{}\n",
+                index + 1, escape(&track.name), escape(&track.kind.to_string()), escape(&track.owner), length, flags
+            ));
+        }
+    }
+
+    data.push_str("\n");
+    data.push_str("[Routes]\n");
+
+    if xpln.lines.is_empty() {
+        let connections = xpln.connections();
+
+        data.push_str(&format!("Count={}\n", connections.len()));
+
+        for (index, &(ref from, ref to)) in connections.iter().enumerate() {
+            data.push_str(&format!("Route{}={};{}\n", index + 1, escape(from), escape(to)));
+        }
+    } else {
+        data.push_str(&format!("Count={}\n", xpln.lines.len()));
+
+        for (index, line) in xpln.lines.iter().enumerate() {
+            data.push_str(&format!("Route{}={};{};{}\n", index + 1, escape(&line.from), escape(&line.to), line.id));
+        }
+    }
+
+    data
+}
+
+/// Escapes field separators, section brackets and line breaks in `value`.
+fn escape(value: &str) -> String {
+    let mut escaped = String::new();
+
+    for c in value.chars() {
+        match c {
+            '\\' | ';' | '[' | ']' => {
+                escaped.push('\\');
+                escaped.push(c);
+            },
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(c)
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use xpln::{Station, Track, TrackKind, Xpln};
+    use super::render;
+
+    #[test]
+    fn render_escapes_values() {
+        let mut xpln    = Xpln::new();
+        let mut station = Station::new("A", "Anst [Nord]\nGleis");
+
+        station.add_track(Track::new("A", "1", TrackKind::Main, "Bahnsteig; durchgehend"));
+        xpln.add_station(station);
+
+        let data = render(&xpln);
+
+        assert!(data.contains("Name=Anst \\[Nord\\]\\nGleis\n"));
+        assert!(data.contains("Track1=1;Main;Bahnsteig\\; durchgehend;;PD\n"));
+    }
+}
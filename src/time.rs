@@ -0,0 +1,197 @@
+use std::fmt;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
+
+use ods;
+
+const SECONDS_PER_DAY: i64 = 86400;
+
+/// Point in time of a session, stored as seconds since midnight of the day
+/// the session starts. Times after the following midnight carry a day offset,
+/// e.g. `00:30` in a session starting at `18:00` is day 1, `00:30`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct XplnTime {
+    seconds: i64
+}
+
+impl XplnTime {
+    pub fn new(day: i64, hour: i64, minute: i64, second: i64) -> XplnTime {
+        XplnTime::from_seconds(day * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second)
+    }
+
+    pub fn from_seconds(seconds: i64) -> XplnTime {
+        XplnTime { seconds: seconds }
+    }
+
+    /// Reads a time from a spreadsheet cell. Time cells are used as is, text
+    /// cells are parsed as `H:MM` or `H:MM:SS`. Empty cells yield `None`.
+    pub fn from_cell(cell: &ods::Cell) -> Result<Option<XplnTime>, String> {
+        match *cell {
+            ods::Cell::Empty => Ok(None),
+            ods::Cell::Time(seconds) => Ok(Some(XplnTime::from_seconds(seconds))),
+            ods::Cell::String(ref text) => {
+                if text.trim().is_empty() {
+                    Ok(None)
+                } else {
+                    XplnTime::from_str(text).map(|time| Some(time))
+                }
+            },
+            ref cell => Err(format!("Not a time value: {:?}", cell))
+        }
+    }
+
+    /// Day offset relative to the session start day.
+    pub fn day(&self) -> i64 {
+        if self.seconds < 0 {
+            (self.seconds + 1) / SECONDS_PER_DAY - 1
+        } else {
+            self.seconds / SECONDS_PER_DAY
+        }
+    }
+
+    pub fn hour(&self) -> i64 {
+        self.time_of_day() / 3600
+    }
+
+    pub fn minute(&self) -> i64 {
+        self.time_of_day() % 3600 / 60
+    }
+
+    pub fn second(&self) -> i64 {
+        self.time_of_day() % 60
+    }
+
+    /// Seconds since midnight, ignoring the day offset.
+    pub fn time_of_day(&self) -> i64 {
+        self.seconds - self.day() * SECONDS_PER_DAY
+    }
+
+    pub fn as_seconds(&self) -> i64 {
+        self.seconds
+    }
+
+    pub fn as_minutes(&self) -> i64 {
+        self.seconds / 60
+    }
+
+    /// Moves a time of the first day that lies before the session `start` to
+    /// the following day, so that times sort in session order even if the
+    /// session crosses midnight.
+    pub fn relative_to(self, start: XplnTime) -> XplnTime {
+        if self.day() == 0 && self.seconds < start.seconds {
+            self + SECONDS_PER_DAY
+        } else {
+            self
+        }
+    }
+
+    /// Seconds elapsed since the session `start`.
+    pub fn since(&self, start: XplnTime) -> i64 {
+        *self - start
+    }
+
+    /// Formats `time` as `HH:MM`, like `Display`, or as `none` if not given.
+    pub fn format_hm(time: Option<XplnTime>, none: &str) -> String {
+        match time {
+            Some(time) => time.to_string(),
+            None => String::from(none)
+        }
+    }
+
+    /// Formats the time as `HH:MM:SS` with hours counted from midnight of the
+    /// session start day, e.g. `24:30:00` for half past midnight.
+    pub fn format_hms(&self) -> String {
+        format!("{:02}:{:02}:{:02}", self.seconds / 3600, self.seconds % 3600 / 60, self.seconds % 60)
+    }
+}
+
+impl FromStr for XplnTime {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<XplnTime, String> {
+        let parts : Vec<&str> = value.trim().split(':').collect();
+
+        if parts.len() < 2 || parts.len() > 3 {
+            return Err(format!("Not a valid time: {}", value));
+        }
+
+        let mut numbers = Vec::new();
+
+        for part in parts.iter() {
+            match i64::from_str(part) {
+                Ok(number) if number >= 0 => numbers.push(number),
+                _ => return Err(format!("Not a valid time: {}", value))
+            }
+        }
+
+        let second = numbers.get(2).map(|s| *s).unwrap_or(0);
+
+        if numbers[1] >= 60 || second >= 60 {
+            return Err(format!("Not a valid time: {}", value));
+        }
+
+        Ok(XplnTime::new(0, numbers[0], numbers[1], second))
+    }
+}
+
+impl fmt::Display for XplnTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{:02}:{:02}", self.hour(), self.minute())
+    }
+}
+
+impl Add<i64> for XplnTime {
+    type Output = XplnTime;
+
+    fn add(self, seconds: i64) -> XplnTime {
+        XplnTime::from_seconds(self.seconds + seconds)
+    }
+}
+
+impl Sub<i64> for XplnTime {
+    type Output = XplnTime;
+
+    fn sub(self, seconds: i64) -> XplnTime {
+        XplnTime::from_seconds(self.seconds - seconds)
+    }
+}
+
+impl Sub for XplnTime {
+    type Output = i64;
+
+    fn sub(self, other: XplnTime) -> i64 {
+        self.seconds - other.seconds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::XplnTime;
+
+    #[test]
+    fn from_str() {
+        assert_eq!(Ok(XplnTime::new(0, 5, 11, 0)), XplnTime::from_str("5:11"));
+        assert_eq!(Ok(XplnTime::new(0, 23, 59, 30)), XplnTime::from_str(" 23:59:30 "));
+        assert_eq!(Ok(XplnTime::new(1, 0, 30, 0)), XplnTime::from_str("24:30"));
+
+        assert!(XplnTime::from_str("5").is_err());
+        assert!(XplnTime::from_str("5:60").is_err());
+        assert!(XplnTime::from_str("5:11:60").is_err());
+        assert!(XplnTime::from_str("5:11:00:00").is_err());
+        assert!(XplnTime::from_str("-1:00").is_err());
+        assert!(XplnTime::from_str("ab:cd").is_err());
+    }
+
+    #[test]
+    fn relative_to() {
+        let start = XplnTime::new(0, 18, 0, 0);
+
+        assert_eq!(XplnTime::new(0, 18, 0, 0), XplnTime::new(0, 18, 0, 0).relative_to(start));
+        assert_eq!(XplnTime::new(0, 23, 30, 0), XplnTime::new(0, 23, 30, 0).relative_to(start));
+        assert_eq!(XplnTime::new(1, 0, 30, 0), XplnTime::new(0, 0, 30, 0).relative_to(start));
+        assert_eq!(XplnTime::new(1, 0, 30, 0), XplnTime::new(1, 0, 30, 0).relative_to(start));
+        assert_eq!(XplnTime::new(0, 0, 30, 0), XplnTime::new(0, 0, 30, 0).relative_to(XplnTime::new(0, 0, 0, 0)));
+    }
+}
@@ -0,0 +1,124 @@
+use report::{Diagnostic, Severity};
+use xpln::{Xpln, StopKind, Train, TrackKind, STATION_TRACK_TABLE, TRAINS_TABLE};
+
+/// Upper bound for plausible train speeds in km/h.
+const MAX_SPEED: usize = 300;
+
+/// Upper bound for plausible axle counts of a single train.
+const MAX_WHEELS: usize = 400;
+
+/// Checks the loaded timetable for consistency: lines and timetable entries
+/// referencing unknown stations or tracks, stops on sidings without a
+/// platform, implausible speeds and axle counts, departures before arrivals,
+/// times going backwards along a train's run, links to unknown successor
+/// trains and trains without timetable entries.
+pub fn validate(xpln: &Xpln) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for line in xpln.lines.iter() {
+        for name in [&line.from, &line.to].iter() {
+            if xpln.station(name).is_none() {
+                diagnostics.push(Diagnostic::new(Severity::Error, "unknown-station",
+                    format!("Line {} {} references unknown station {}", line.id, line.name(), name)
+                ).at(STATION_TRACK_TABLE, line.row, Some(9)));
+            }
+        }
+    }
+
+    for train in xpln.sorted_trains() {
+        validate_references(xpln, train, &mut diagnostics);
+        validate_times(train, &mut diagnostics);
+
+        if let Some(speed) = train.speed {
+            if speed == 0 || speed > MAX_SPEED {
+                diagnostics.push(Diagnostic::new(Severity::Warning, "implausible-speed",
+                    format!("Train {} has an implausible speed of {} km/h", train.name(), speed)
+                ).at(TRAINS_TABLE, train.row, Some(7)));
+            }
+        }
+
+        if let Some(wheels) = train.wheels {
+            if wheels == 0 || wheels > MAX_WHEELS {
+                diagnostics.push(Diagnostic::new(Severity::Warning, "implausible-wheels",
+                    format!("Train {} has an implausible axle count of {}", train.name(), wheels)
+                ).at(TRAINS_TABLE, train.row, Some(7)));
+            }
+        }
+
+        if let Some(successor) = train.successor {
+            if xpln.train(successor).is_none() {
+                diagnostics.push(Diagnostic::new(Severity::Error, "unknown-train",
+                    format!("Train {} continues as unknown train {}", train.name(), successor)
+                ).at(TRAINS_TABLE, train.row, Some(11)));
+            }
+        }
+
+        if train.timetables.is_empty() {
+            diagnostics.push(Diagnostic::new(Severity::Warning, "empty-train",
+                format!("Train {} has no timetable entries", train.name())
+            ).at(TRAINS_TABLE, train.row, Some(1)));
+        }
+    }
+
+    diagnostics
+}
+
+fn validate_references(xpln: &Xpln, train: &Train, diagnostics: &mut Vec<Diagnostic>) {
+    for timetable in train.timetables.iter() {
+        let station = match xpln.station(&timetable.station) {
+            Some(station) => station,
+            None => {
+                diagnostics.push(Diagnostic::new(Severity::Error, "unknown-station",
+                    format!("Train {} calls at unknown station {}", train.name(), timetable.station)
+                ).at(TRAINS_TABLE, timetable.row, Some(3)));
+                continue;
+            }
+        };
+
+        if timetable.track.is_empty() { continue }
+
+        let track = match station.track(&timetable.track) {
+            Some(track) => track,
+            None => {
+                diagnostics.push(Diagnostic::new(Severity::Error, "unknown-track",
+                    format!("Train {} uses unknown track {} in {}", train.name(), timetable.track, station.name)
+                ).at(TRAINS_TABLE, timetable.row, Some(4)));
+                continue;
+            }
+        };
+
+        // Trains starting, ending or reversing on a siding are usually
+        // shunted, e.g. to and from a depot, and not checked
+        if timetable.kind == StopKind::Stop && track.kind == TrackKind::Siding && !track.platform {
+            diagnostics.push(Diagnostic::new(Severity::Warning, "no-platform",
+                format!("Train {} stops on siding {} in {} without a platform", train.name(), track.name, station.name)
+            ).at(TRAINS_TABLE, timetable.row, Some(4)));
+        }
+    }
+}
+
+fn validate_times(train: &Train, diagnostics: &mut Vec<Diagnostic>) {
+    let mut previous = None;
+
+    for timetable in train.timetables.iter() {
+        if let (Some(arrival), Some(departure)) = (timetable.arrival, timetable.departure) {
+            if departure < arrival {
+                diagnostics.push(Diagnostic::new(Severity::Error, "departure-before-arrival",
+                    format!("Train {} departs {} at {} before arriving at {}",
+                        train.name(), timetable.station, departure, arrival)
+                ).at(TRAINS_TABLE, timetable.row, Some(6)));
+            }
+        }
+
+        if let (Some(last), Some(time)) = (previous, timetable.time()) {
+            if time < last {
+                diagnostics.push(Diagnostic::new(Severity::Error, "non-monotonic-time",
+                    format!("Train {} reaches {} at {}, before leaving the previous stop at {}",
+                        train.name(), timetable.station, time, last)
+                ).at(TRAINS_TABLE, timetable.row, Some(5)));
+            }
+        }
+
+        previous = timetable.departure.or(timetable.arrival).or(previous);
+    }
+}
@@ -1,30 +1,97 @@
-use std::str::FromStr;
-use std::num::ParseIntError;
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter, Error};
 use std::iter::Iterator;
 
 use ods;
+use network::{self, StationGraph};
+use report::{Diagnostic, LoadError, LoadReport, Severity};
+
+pub use network::Line;
+pub use time::XplnTime;
+
+pub const STATION_TRACK_TABLE: &'static str = "StationTrack";
+pub const TRAINS_TABLE: &'static str = "Trains";
+
+/// Header row of the Trains sheet as written by XPLN.
+pub const TRAINS_HEADER: [&'static str; 11] = [
+    "Train number", "enum", "train prefix/ station ", "train suffix/ track/ station", "Arrival",
+    "Departure", "Speed/ Group/ wheels", "Object", "type", "Trainname", "remark"
+];
+
+/// Types of Trains rows that are not interpreted but kept per train.
+const OBJECT_TYPES: [&'static str; 3] = ["locomotive", "trainset", "job"];
 
 #[derive(Debug)]
 pub struct Xpln {
     pub trains: HashMap<usize, Train>,
     pub stations: HashMap<String, Station>,
+    /// Lines decoded from the `Strecke` definitions, in sheet order.
+    pub lines: Vec<Line>,
+    /// Times before the session start are moved to the following day.
+    pub session_start: XplnTime,
 }
 
 #[derive(Debug)]
 pub struct Train{
     pub number: usize,
-    pub class: String,
+    pub designation: TrainName,
     pub remark: String,
-    pub timetables: Vec<Timetable>
+    pub timetables: Vec<Timetable>,
+    /// Maximum speed in km/h from the traindef's `Speed` column.
+    pub speed: Option<usize>,
+    /// Train group from the train's `group` row.
+    pub group: Option<TrainGroup>,
+    /// Axle count from the train's `wheel` row.
+    pub wheels: Option<usize>,
+    /// Train continuing with this train's stock, given as `=> <train>` in the
+    /// traindef remark or the remark of the last timetable entry.
+    pub successor: Option<usize>,
+    /// Key from the traindef's `Object` column, e.g. `000000.FD 61`.
+    pub object: String,
+    /// The train's `locomotive`, `trainset` and `job` rows in sheet order.
+    /// They are not interpreted, only kept to write the sheet back.
+    pub objects: Vec<ods::Row>,
+    /// Spreadsheet row of the traindef, `0` if not loaded from a sheet.
+    pub row: usize
+}
+
+/// Train designation from the traindef's `Trainname` column, e.g. `FD 61`
+/// or `Dg 61 71`, together with the prefix and suffix columns.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TrainName {
+    /// Designation as written in the sheet, including prefix and suffix.
+    pub original: String,
+    /// Train category, e.g. `FD` or `Dg`.
+    pub category: String,
+    /// Line number following the category, e.g. `61` in `Dg 61 71`.
+    pub line: Option<String>,
+    /// Prefix from the traindef's prefix column.
+    pub prefix: Option<String>,
+    /// Suffix from the traindef's suffix column or the remainder of the
+    /// designation, e.g. `71` in `Dg 61 71`.
+    pub suffix: Option<String>
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TrainGroup {
+    pub id: usize,
+    pub name: String
 }
 
 #[derive(Debug)]
 pub struct Station {
     pub name: String,
     pub remark: String,
-    pub tracks: Vec<Track>
+    pub tracks: Vec<Track>,
+    /// Position from the `PositionX`/`PositionY` columns, if given.
+    pub position: Option<Position>
+}
+
+/// Position of a station on the layout schematic in XPLN units.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Position {
+    pub x: f64,
+    pub y: f64
 }
 
 #[derive(Debug)]
@@ -32,6 +99,25 @@ pub struct Track {
     pub name: String,
     pub owner: String,
     pub station: String,
+    pub kind: TrackKind,
+    /// Usable length from the `PositionY/Length` column, if given.
+    pub length: Option<f64>,
+    /// Track has a platform, e.g. noted as `Hausbahnsteig`.
+    pub platform: bool,
+    /// Track is a through track. XPLN has no column for this, so it is
+    /// taken from the notes: any track noted as `durchgehend`, e.g.
+    /// `Bahnsteig durchgehend`, counts as one.
+    pub through: bool,
+}
+
+/// Track type from the XPLN `SubType` column.
+#[derive(Clone, PartialEq, Debug)]
+pub enum TrackKind {
+    Main,
+    Siding,
+    FiddleYard,
+    Platform,
+    Other(String)
 }
 
 #[derive(Debug)]
@@ -40,62 +126,256 @@ pub struct Timetable {
     pub track: String,
     pub remark: String,
     pub station: String,
-    pub arrival: String,
-    pub departure: String,
+    pub arrival: Option<XplnTime>,
+    pub departure: Option<XplnTime>,
+    /// Position within the train's run from the `enum` column, e.g. `10`,
+    /// `20`, `30`.
+    pub sequence: Option<usize>,
+    /// Role of the entry in the train's run, see `Train::update_stop_kinds`.
+    pub kind: StopKind,
+    /// Spreadsheet row of the entry, `0` if not loaded from a sheet.
+    pub row: usize,
+}
+
+/// Role of a timetable entry within a train's run.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StopKind {
+    /// First entry of the run.
+    Origin,
+    /// Last entry of the run.
+    Terminus,
+    /// Train stops to serve the station, also if arrival and departure are
+    /// equal.
+    Stop,
+    /// Train passes without stopping: exactly one time is given.
+    Pass,
+    /// Train changes direction, i.e. comes from and continues to the same
+    /// station.
+    Reverse
 }
 
 impl Xpln{
     pub fn new() -> Xpln {
         let xpln = Xpln {
             trains: HashMap::new(),
-            stations: HashMap::new()
+            stations: HashMap::new(),
+            lines: Vec::new(),
+            session_start: XplnTime::new(0, 0, 0, 0)
         };
 
         return xpln;
     }
 
-    fn add_train(&mut self, train: Train) {
+    pub fn add_train(&mut self, train: Train) {
         self.trains.insert(train.number, train);
     }
 
-    fn add_station(&mut self, station: Station) {
+    pub fn add_station(&mut self, station: Station) {
         self.stations.insert(String::from(station.name.as_ref()), station);
     }
 
-    fn get_station_mut(&mut self, name: &str) -> Option<&mut Station> {
+    pub fn get_station_mut(&mut self, name: &str) -> Option<&mut Station> {
         return self.stations.get_mut(name);
     }
 
-    pub fn load(&mut self, document: &ods::Spreadsheet) {
-        self.load_stations(document);
-        self.load_station_tracks(document);
-        self.load_traindefs(document);
-        self.load_timetables(document);
+    pub fn train(&self, number: usize) -> Option<&Train> {
+        return self.trains.get(&number);
+    }
+
+    pub fn station(&self, name: &str) -> Option<&Station> {
+        return self.stations.get(name);
+    }
+
+    /// Returns all trains ordered by train number.
+    pub fn sorted_trains(&self) -> Vec<&Train> {
+        let mut trains : Vec<&Train> = self.trains.values().collect();
+        trains.sort_by(|t0, t1| t0.number.cmp(&t1.number));
+        return trains;
+    }
+
+    /// Returns all stations ordered by name.
+    pub fn sorted_stations(&self) -> Vec<&Station> {
+        let mut stations : Vec<&Station> = self.stations.values().collect();
+        stations.sort_by(|s0, s1| s0.name.cmp(&s1.name));
+        return stations;
+    }
+
+    /// Returns the other direction of `line`: the line with the same id or,
+    /// if there is none, a line connecting the same stations in reverse.
+    pub fn opposite(&self, line: &Line) -> Option<&Line> {
+        let same_id = self.lines.iter().find(|other| other.id == line.id && other.is_opposite(line));
+
+        same_id.or_else(|| self.lines.iter().find(|other| other.is_opposite(line)))
+    }
+
+    /// Builds the graph of stations connected by lines.
+    pub fn graph(&self) -> StationGraph {
+        StationGraph::new(&self.lines)
+    }
+
+    /// Returns all pairs of stations a train runs between without calling
+    /// elsewhere, in the direction of travel and sorted by name.
+    pub fn connections(&self) -> Vec<(String, String)> {
+        let mut connections = Vec::new();
+
+        for train in self.trains.values() {
+            for pair in train.timetables.windows(2) {
+                let connection = (pair[0].station.clone(), pair[1].station.clone());
+
+                if connection.0 != connection.1 && !connections.contains(&connection) {
+                    connections.push(connection);
+                }
+            }
+        }
+
+        connections.sort();
+        connections
+    }
+
+    /// Builds the StationTrack and Trains tables of an XPLN spreadsheet from
+    /// the model, so that loading them yields the same model again. Times
+    /// are written as time of day. Rows are written in XPLN's order per
+    /// train: traindef, timetable, the kept objects, wheel and group.
+    ///
+    /// Not kept are the traindef's times, which are taken from the first
+    /// and last timetable entry, the times and stations of `wheel` and
+    /// `group` rows and the station's `SubType`.
+    pub fn to_spreadsheet(&self) -> ods::Spreadsheet {
+        let mut spreadsheet = ods::Spreadsheet::new();
+        let mut stations    = ods::Table::new(STATION_TRACK_TABLE);
+        let mut trains      = ods::Table::new(TRAINS_TABLE);
+
+        stations.push(strings(&network::STATION_TRACK_HEADER));
+
+        let lines = network::encode_lines(&self.lines);
+
+        for (index, station) in self.sorted_stations().into_iter().enumerate() {
+            let (x, y) = match station.position {
+                Some(position) => (ods::Cell::Float(position.x), ods::Cell::Float(position.y)),
+                None => (ods::Cell::Empty, ods::Cell::Empty)
+            };
+
+            stations.push(vec![
+                string(&station.name), ods::Cell::Empty, x, y, string(&station.remark), string("Station"),
+                ods::Cell::Empty, ods::Cell::Empty,
+                if index == 0 { string(&lines) } else { ods::Cell::Empty }
+            ]);
+
+            for track in station.tracks.iter() {
+                stations.push(vec![
+                    string(&station.name), ods::Cell::Empty, string(&track.name),
+                    track.length.map(ods::Cell::Float).unwrap_or(ods::Cell::Empty),
+                    ods::Cell::Empty, string("Track"), string(track.kind.name()), string(&track.owner)
+                ]);
+            }
+        }
+
+        trains.push(strings(&TRAINS_HEADER));
+
+        for train in self.sorted_trains() {
+            let number = ods::Cell::Float(train.number as f64);
+            let name   = string(train.designation.sheet_name());
+
+            let first = train.timetables.first().and_then(|t| t.departure.or(t.arrival));
+            let last  = train.timetables.last().and_then(|t| t.arrival.or(t.departure));
+
+            trains.push(vec![
+                number.clone(), ods::Cell::Float(0.0),
+                string(train.designation.prefix.as_ref().map(|p| &p[..]).unwrap_or("")),
+                string(train.designation.suffix.as_ref().map(|s| &s[..]).unwrap_or("")),
+                time(first), time(last),
+                train.speed.map(|speed| ods::Cell::Float(speed as f64)).unwrap_or(ods::Cell::Empty),
+                string(&train.object), string("traindef"), name.clone(), string(&train.remark)
+            ]);
+
+            for (index, timetable) in train.timetables.iter().enumerate() {
+                let sequence = timetable.sequence.unwrap_or((index + 1) * 10);
+
+                trains.push(vec![
+                    number.clone(), ods::Cell::Float(sequence as f64), string(&timetable.station),
+                    string(&timetable.track), time(timetable.arrival), time(timetable.departure),
+                    ods::Cell::Empty, ods::Cell::Empty, string("timetable"), name.clone(),
+                    string(&timetable.remark)
+                ]);
+            }
+
+            for object in train.objects.iter() {
+                trains.push(object.cells.clone());
+            }
+
+            if let Some(wheels) = train.wheels {
+                trains.push(vec![
+                    number.clone(), ods::Cell::Empty, ods::Cell::Empty, ods::Cell::Empty, ods::Cell::Empty,
+                    ods::Cell::Empty, ods::Cell::Float(wheels as f64), ods::Cell::Empty, string("wheel")
+                ]);
+            }
+
+            if let Some(ref group) = train.group {
+                trains.push(vec![
+                    number.clone(), ods::Cell::Empty, ods::Cell::Empty, ods::Cell::Empty, ods::Cell::Empty,
+                    ods::Cell::Empty, ods::Cell::Float(group.id as f64), string(&group.name), string("group")
+                ]);
+            }
+        }
+
+        spreadsheet.push(stations);
+        spreadsheet.push(trains);
+        spreadsheet
     }
 
-    fn load_stations(&mut self, document: &ods::Spreadsheet) {
-        let table = document
-            .get("StationTrack")
-            .expect("PANIC: Missing StationTrack table.");
+    /// Loads stations, tracks, trains and timetables from `document`.
+    /// Invalid rows are skipped and reported in the returned `LoadReport`.
+    pub fn load(&mut self, document: &ods::Spreadsheet) -> Result<LoadReport, LoadError> {
+        let mut report = LoadReport::new();
+
+        let stations = try!(get_table(document, STATION_TRACK_TABLE));
+        let trains   = try!(get_table(document, TRAINS_TABLE));
+
+        self.load_stations(stations, &mut report);
+        self.load_station_tracks(stations, &mut report);
+        self.load_traindefs(trains, &mut report);
+        self.load_train_attributes(trains, &mut report);
+        self.load_train_objects(trains, &mut report);
+        self.load_timetables(trains, &mut report);
+        self.order_timetables(&mut report);
+        self.load_successors(&mut report);
 
-        println!("Loading station objects...");
+        Ok(report)
+    }
 
+    fn load_stations(&mut self, table: &ods::Table, report: &mut LoadReport) {
         for row in table.rows() {
             if row.values.len() < 6 { continue }
 
             match row.values[5].as_ref() {
                 "Station" => {
-                    let station = Station::parse(
-                        &row.values[0],
-                        &row.values[4]
-                    );
+                    let mut station = Station::new(&row.values[0][..], &row.values[4][..]);
 
-                    match station {
-                        Ok(station) => {
-                            self.add_station(station);
+                    // The position is optional, keep the station without it
+                    match Position::parse(&row.cell(2), &row.cell(3)) {
+                        Ok(position) => {
+                            station.position = position;
                         },
                         Err(err) => {
-                            println!("ERR: Invalid station object: {}\n     {}", err, row);
+                            report.push(err.warning("invalid-position", "Ignoring station position").at(table.name(), row.number, Some(err.column)));
+                        }
+                    }
+
+                    self.add_station(station);
+
+                    // The first station carries the encoded line definitions
+                    if row.values.len() > 8 && row.values[8].starts_with('/') {
+                        match network::parse_lines(&row.values[8]) {
+                            Ok(lines) => {
+                                for mut line in lines.into_iter() {
+                                    line.row = row.number;
+                                    self.lines.push(line);
+                                }
+                            },
+                            Err(err) => {
+                                report.push(Diagnostic::new(Severity::Error, "invalid-line", err)
+                                    .at(table.name(), row.number, Some(9)));
+                            }
                         }
                     }
                 },
@@ -104,30 +384,37 @@ impl Xpln{
         }
     }
 
-    fn load_station_tracks(&mut self, document: &ods::Spreadsheet) {
-        let table = document
-            .get("StationTrack")
-            .expect("PANIC: Missing StationTrack table.");
-
-        println!("Loading station track objects...");
-
+    fn load_station_tracks(&mut self, table: &ods::Table, report: &mut LoadReport) {
         for row in table.rows() {
             if row.values.len() < 7 { continue }
 
             match row.values[5].as_ref() {
                 "Track" => {
-                    let track = Track::parse(
-                        &row.values[0],
-                        &row.values[6],
-                        &row.values.get(7).unwrap_or(&String::new())
+                    let mut track = Track::new(
+                        &row.values[0][..],
+                        &row.values[2][..],
+                        TrackKind::parse(&row.values[6]),
+                        &row.values.get(7).unwrap_or(&String::new())[..]
                     );
 
+                    // The length is optional, keep the track without it
+                    match parse_length(&row.cell(3)) {
+                        Ok(length) => {
+                            track.length = length;
+                        },
+                        Err(err) => {
+                            report.push(err.warning("invalid-length", "Ignoring track length").at(table.name(), row.number, Some(err.column)));
+                        }
+                    }
+
                     match self.get_station_mut(&track.station) {
                         Some(station) => {
                             station.add_track(track);
                         },
                         None => {
-                            println!("ERR: Illegal station reference in track object.\n     {}", row);
+                            report.push(Diagnostic::new(Severity::Error, "unknown-station",
+                                format!("Illegal station reference in track object: {}", track.station)
+                            ).at(table.name(), row.number, Some(1)));
                         }
                     };
                 },
@@ -136,13 +423,7 @@ impl Xpln{
         }
     }
 
-    fn load_traindefs(&mut self, document: &ods::Spreadsheet) {
-        let table = document
-            .get("Trains")
-            .expect("PANIC: Missing Trains table.");
-
-        println!("Loading traindef objects...");
-
+    fn load_traindefs(&mut self, table: &ods::Table, report: &mut LoadReport) {
         for row in table.rows() {
             // Require at least 10 fields for matching and parsing
             if row.values.len() < 10 { continue }
@@ -150,17 +431,37 @@ impl Xpln{
             match row.values[8].as_ref() {
                 "traindef" => {
                     let train = Train::parse(
-                        &row.values[0],
+                        &row.cell(0),
                         &row.values[9],
+                        &row.values[2],
+                        &row.values[3],
                         &row.values.get(10).unwrap_or(&String::new())
                     );
 
                     match train {
-                        Ok(train) => {
+                        Ok(ref train) if self.trains.contains_key(&train.number) => {
+                            report.push(Diagnostic::new(Severity::Error, "duplicate-train",
+                                format!("Duplicate traindef for train {}, first defined in row {}",
+                                    train.number, self.trains[&train.number].row)
+                            ).at(table.name(), row.number, Some(1)));
+                        },
+                        Ok(mut train) => {
+                            // The speed is optional, keep the train without it
+                            match parse_speed(&row.cell(6)) {
+                                Ok(speed) => {
+                                    train.speed = speed;
+                                },
+                                Err(err) => {
+                                    report.push(err.warning("invalid-speed", "Ignoring train speed").at(table.name(), row.number, Some(err.column)));
+                                }
+                            }
+
+                            train.object = row.values[7].clone();
+                            train.row    = row.number;
                             self.add_train(train);
                         },
                         Err(err) => {
-                            println!("ERR: Invalid traindef: {}\n     {}", err, row);
+                            report.push(err.diagnostic("invalid-traindef", "Invalid traindef").at(table.name(), row.number, Some(err.column)));
                         }
                     };
 
@@ -170,22 +471,68 @@ impl Xpln{
         }
     }
 
-    fn load_timetables(&mut self, document: &ods::Spreadsheet) {
-        let table = document
-            .get("Trains")
-            .expect("PANIC: Missing Trains table.");
+    fn load_train_attributes(&mut self, table: &ods::Table, report: &mut LoadReport) {
+        for row in table.rows() {
+            if row.values.len() < 9 { continue }
+
+            let kind : &str = &row.values[8];
+
+            if kind != "group" && kind != "wheel" { continue }
+
+            let value = match row.cell(6).as_usize() {
+                Some(value) => value,
+                None => {
+                    report.push(Diagnostic::new(Severity::Error, "invalid-train-attribute",
+                        format!("Invalid {} value: {:?}", kind, row.cell(6))
+                    ).at(table.name(), row.number, Some(7)));
+                    continue;
+                }
+            };
 
-        println!("Loading timetable objects...");
+            let train = match row.cell(0).as_usize().and_then(|number| self.trains.get_mut(&number)) {
+                Some(train) => train,
+                None => {
+                    report.push(Diagnostic::new(Severity::Error, "unknown-train",
+                        format!("Illegal train reference in {} object: {}", kind, row.values[0])
+                    ).at(table.name(), row.number, Some(1)));
+                    continue;
+                }
+            };
+
+            if kind == "group" {
+                train.group = Some(TrainGroup { id: value, name: row.values[7].clone() });
+            } else {
+                train.wheels = Some(value);
+            }
+        }
+    }
 
+    fn load_train_objects(&mut self, table: &ods::Table, report: &mut LoadReport) {
         for row in table.rows() {
-            if row.values.len() < 11 { continue }
+            if row.values.len() < 9 || !OBJECT_TYPES.contains(&&row.values[8][..]) { continue }
+
+            match row.cell(0).as_usize().and_then(|number| self.trains.get_mut(&number)) {
+                Some(train) => train.objects.push(row.clone()),
+                None => {
+                    report.push(Diagnostic::new(Severity::Error, "unknown-train",
+                        format!("Illegal train reference in {} object: {}", row.values[8], row.values[0])
+                    ).at(table.name(), row.number, Some(1)));
+                }
+            }
+        }
+    }
+
+    fn load_timetables(&mut self, table: &ods::Table, report: &mut LoadReport) {
+        for row in table.rows() {
+            // The remark column is optional
+            if row.values.len() < 10 { continue }
 
             match row.values[8].as_ref() {
                 "timetable" => {
-                    let timetable = match Timetable::parse(&row.values) {
+                    let timetable = match Timetable::parse(row, self.session_start) {
                         Ok(timetable) => timetable,
                         Err(err) => {
-                            println!("ERR: Invalid timetable: {}\n     {}", err, row);
+                            report.push(err.diagnostic("invalid-timetable", "Invalid timetable").at(table.name(), row.number, Some(err.column)));
                             continue;
                         }
                     };
@@ -195,7 +542,9 @@ impl Xpln{
                             train.timetables.push(timetable);
                         },
                         None => {
-                            println!("ERR: Illegal train reference in timetable object: {}\n     {}", row.values[0], row);
+                            report.push(Diagnostic::new(Severity::Error, "unknown-train",
+                                format!("Illegal train reference in timetable object: {}", row.values[0])
+                            ).at(table.name(), row.number, Some(1)));
                         }
                     };
                 },
@@ -203,6 +552,147 @@ impl Xpln{
             }
         }
     }
+
+    /// Reads the explicit successor links of all trains. A link in the
+    /// traindef remark takes precedence over one in the last timetable entry.
+    fn load_successors(&mut self, report: &mut LoadReport) {
+        let mut numbers : Vec<usize> = self.trains.keys().cloned().collect();
+        numbers.sort();
+
+        for number in numbers {
+            let train = self.trains.get_mut(&number).unwrap();
+
+            let mut sources = vec![(train.remark.clone(), train.row)];
+
+            if let Some(last) = train.timetables.last() {
+                sources.push((last.remark.clone(), last.row));
+            }
+
+            for (remark, row) in sources {
+                match parse_successor(&remark) {
+                    Some(Ok(successor)) => {
+                        train.successor = Some(successor);
+                        break;
+                    },
+                    Some(Err(err)) => {
+                        report.push(Diagnostic::new(Severity::Error, "invalid-successor", err)
+                            .at(TRAINS_TABLE, row, Some(11)));
+                    },
+                    None => ()
+                }
+            }
+        }
+    }
+
+    /// Sorts each train's timetable entries by their `enum` sequence number
+    /// and derives their stop kinds. Entries without a number keep their row
+    /// order after all numbered entries. Duplicate sequence numbers and gaps,
+    /// i.e. steps larger than the regular step of the run, are reported.
+    fn order_timetables(&mut self, report: &mut LoadReport) {
+        let mut numbers : Vec<usize> = self.trains.keys().cloned().collect();
+        numbers.sort();
+
+        for number in numbers {
+            let train = self.trains.get_mut(&number).unwrap();
+
+            train.timetables.sort_by(|t0, t1| {
+                (t0.sequence.is_none(), t0.sequence, t0.row).cmp(&(t1.sequence.is_none(), t1.sequence, t1.row))
+            });
+
+            let sequences : Vec<(usize, usize)> = train.timetables.iter()
+                .filter_map(|t| t.sequence.map(|sequence| (sequence, t.row)))
+                .collect();
+
+            let step = regular_step(&sequences.iter().map(|s| s.0).collect::<Vec<usize>>());
+
+            for pair in sequences.windows(2) {
+                let ((previous, _), (sequence, row)) = (pair[0], pair[1]);
+
+                if sequence == previous {
+                    report.push(Diagnostic::new(Severity::Error, "duplicate-sequence",
+                        format!("Train {} has more than one timetable entry numbered {}", train.name(), sequence)
+                    ).at(TRAINS_TABLE, row, Some(2)));
+                } else if step.map(|step| sequence - previous > step).unwrap_or(false) {
+                    report.push(Diagnostic::new(Severity::Warning, "sequence-gap",
+                        format!("Train {} skips from timetable entry {} to {}", train.name(), previous, sequence)
+                    ).at(TRAINS_TABLE, row, Some(2)));
+                }
+            }
+
+            train.update_stop_kinds();
+        }
+    }
+}
+
+/// Parses a successor link `=> <train>` from a remark. Returns `None` if the
+/// remark contains no link.
+pub fn parse_successor(remark: &str) -> Option<Result<usize, String>> {
+    let start = match remark.find("=>") {
+        Some(index) => index + 2,
+        None => return None
+    };
+
+    let number : String = remark[start..].trim().chars().take_while(|c| c.is_digit(10)).collect();
+
+    Some(number.parse().map_err(|_| format!("Invalid successor link: {}", &remark[start - 2..])))
+}
+
+/// Step the entries were numbered with, i.e. the first difference between
+/// consecutive sequence numbers. Entries inserted later (e.g. `25` between
+/// `20` and `30`) only make smaller steps and are not mistaken for it. A gap
+/// right after the first entry is taken as the step and not reported.
+fn regular_step(sequences: &[usize]) -> Option<usize> {
+    sequences.windows(2).map(|pair| pair[1] - pair[0]).find(|&step| step > 0)
+}
+
+fn string(value: &str) -> ods::Cell {
+    if value.is_empty() { ods::Cell::Empty } else { ods::Cell::String(String::from(value)) }
+}
+
+fn strings(values: &[&str]) -> Vec<ods::Cell> {
+    values.iter().map(|value| string(value)).collect()
+}
+
+fn time(time: Option<XplnTime>) -> ods::Cell {
+    match time {
+        Some(time) => ods::Cell::Time(time.time_of_day()),
+        None => ods::Cell::Empty
+    }
+}
+
+fn get_table<'a>(document: &'a ods::Spreadsheet, name: &str) -> Result<&'a ods::Table, LoadError> {
+    match document.get(name) {
+        Some(table) => Ok(table),
+        None => Err(LoadError::MissingTable(String::from(name)))
+    }
+}
+
+/// Error returned when a row cannot be parsed into a model object. `column`
+/// is the 1-based spreadsheet column holding the invalid value.
+#[derive(Debug)]
+pub struct ParseError {
+    pub column: usize,
+    pub message: String
+}
+
+impl ParseError {
+    pub fn new<S: Into<String>>(column: usize, message: S) -> ParseError {
+        ParseError { column: column, message: message.into() }
+    }
+
+    fn diagnostic(&self, code: &'static str, context: &str) -> Diagnostic {
+        Diagnostic::new(Severity::Error, code, format!("{}: {}", context, self.message))
+    }
+
+    fn warning(&self, code: &'static str, context: &str) -> Diagnostic {
+        Diagnostic::new(Severity::Warning, code, format!("{}: {}", context, self.message))
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.message)
+    }
 }
 
 impl Display for Xpln {
@@ -210,22 +700,14 @@ impl Display for Xpln {
         try!(writeln!(f, "Xpln ["));
         try!(writeln!(f, "  Stations:"));
 
-        let mut stations : Vec<_> = self.stations.keys().collect();
-        stations.sort();
-
-        for name in stations {
-            let station = self.stations.get(name).unwrap();
+        for station in self.sorted_stations() {
             try!(writeln!(f, "    {}", station));
         }
 
         try!(writeln!(f, ""));
         try!(writeln!(f, "  Trains:"));
 
-        let mut trains : Vec<_> = self.trains.keys().collect();
-        trains.sort();
-
-        for number in trains {
-            let train = self.trains.get(number).unwrap();
+        for train in self.sorted_trains() {
             try!(writeln!(f, "    {}", train));
         }
 
@@ -236,50 +718,197 @@ impl Display for Xpln {
 }
 
 impl Train {
-    fn new<S0, S1>(number: usize, class: S0, remark: S1) -> Train
-                where S0 : Into<String>, S1 : Into<String> {
+    pub fn new<S: Into<String>>(number: usize, designation: TrainName, remark: S) -> Train {
         Train {
             number: number,
-            class: class.into(),
+            designation: designation,
             remark: remark.into(),
-            timetables: Vec::new()
+            timetables: Vec::new(),
+            speed: None,
+            group: None,
+            wheels: None,
+            successor: None,
+            object: String::new(),
+            objects: Vec::new(),
+            row: 0
         }
     }
 
+    /// Train category, e.g. `FD`.
+    pub fn class(&self) -> &str {
+        &self.designation.category
+    }
+
+    /// Short name made of category and train number, e.g. `FD 61`.
     pub fn name(&self) -> String {
-        format!("{} {}", self.class, self.number)
+        format!("{} {}", self.class(), self.number)
+    }
+
+    /// Designation as written in the sheet or the short name if there is
+    /// none.
+    pub fn original_name(&self) -> String {
+        if self.designation.original.is_empty() {
+            self.name()
+        } else {
+            self.designation.original.clone()
+        }
     }
 
-    fn parse(number: &str, name: &str, remark: &str) -> Result<Train, ParseIntError> {
-        let class : String = name.chars().take_while(|c| c != &' ').collect();
+    /// Returns the stop before the timetable entry at `index` or `None` if
+    /// the train starts there.
+    pub fn previous(&self, index: usize) -> Option<&Timetable> {
+        if index == 0 { None } else { self.timetables.get(index - 1) }
+    }
 
-        Ok(Train::new(try!(usize::from_str(number)), class, remark))
+    /// Returns the stop after the timetable entry at `index` or `None` if
+    /// the train ends there.
+    pub fn next(&self, index: usize) -> Option<&Timetable> {
+        self.timetables.get(index + 1)
+    }
+
+    /// Derives the stop kind of every timetable entry from its position in
+    /// the run and the times given. Must be called again after changing the
+    /// order of `timetables`.
+    pub fn update_stop_kinds(&mut self) {
+        let kinds : Vec<StopKind> = (0..self.timetables.len()).map(|index| {
+            let timetable = &self.timetables[index];
+
+            match (self.previous(index), self.next(index)) {
+                (None, _) => StopKind::Origin,
+                (_, None) => StopKind::Terminus,
+                (Some(previous), Some(next)) if previous.station == next.station => StopKind::Reverse,
+                _ => match (timetable.arrival, timetable.departure) {
+                    (Some(_), None) | (None, Some(_)) => StopKind::Pass,
+                    _ => StopKind::Stop
+                }
+            }
+        }).collect();
+
+        for (timetable, kind) in self.timetables.iter_mut().zip(kinds.into_iter()) {
+            timetable.kind = kind;
+        }
+    }
+
+    pub fn parse(number: &ods::Cell, name: &str, prefix: &str, suffix: &str, remark: &str) -> Result<Train, ParseError> {
+        let designation = TrainName::parse(name, prefix, suffix);
+
+        match number.as_usize() {
+            Some(number) => Ok(Train::new(number, designation, remark)),
+            None => Err(ParseError::new(1, format!("Invalid train number: {:?}", number)))
+        }
     }
 }
 
 impl Display for Train {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        try!(write!(f, "{:>3} {:>4} : {}", self.class, self.number, self.remark));
+        try!(write!(f, "{:>3} {:>4} : {}", self.class(), self.number, self.remark));
         Ok(())
     }
 }
 
+impl TrainName {
+    /// Splits `name` into category, line number and suffix. Prefix and
+    /// suffix columns are kept as given; a non-empty suffix column takes
+    /// precedence over the remainder of the designation.
+    pub fn parse(name: &str, prefix: &str, suffix: &str) -> TrainName {
+        let mut parts = name.split_whitespace();
+
+        let category = parts.next().unwrap_or("");
+        let line     = parts.next();
+        let rest     = parts.collect::<Vec<&str>>().join(" ");
+
+        let original = [prefix.trim(), name.trim(), suffix.trim()].iter()
+            .filter(|part| !part.is_empty())
+            .cloned()
+            .collect::<Vec<&str>>()
+            .join(" ");
+
+        TrainName {
+            original: original,
+            category: String::from(category),
+            line: line.map(String::from),
+            prefix: non_empty(prefix),
+            suffix: non_empty(suffix).or(non_empty(&rest))
+        }
+    }
+
+    /// Designation without the prefix and suffix, i.e. the traindef's
+    /// `Trainname` column it was parsed from. A suffix taken from the
+    /// designation is removed as well and written to the suffix column
+    /// instead, which parses to the same designation.
+    pub fn sheet_name(&self) -> &str {
+        let mut name : &str = &self.original;
+
+        if let Some(ref prefix) = self.prefix {
+            if name.starts_with(&prefix[..]) { name = name[prefix.len()..].trim() }
+        }
+
+        if let Some(ref suffix) = self.suffix {
+            if name.ends_with(&suffix[..]) { name = name[..name.len() - suffix.len()].trim() }
+        }
+
+        name
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() { None } else { Some(String::from(value)) }
+}
+
+impl Display for TrainName {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.original)
+    }
+}
+
 impl Station {
-    fn new<S: Into<String>>(name: S, remark: S) -> Station {
+    pub fn new<S: Into<String>>(name: S, remark: S) -> Station {
         Station {
             name: name.into(),
             remark: remark.into(),
-            tracks: Vec::new()
+            tracks: Vec::new(),
+            position: None
         }
     }
 
-    fn parse(name: &str, remark: &str) -> Result<Station, ParseIntError> {
-        Ok(Station::new(name, remark))
+    /// Straight-line distance to `other` or `None` if a position is missing.
+    pub fn distance(&self, other: &Station) -> Option<f64> {
+        match (self.position, other.position) {
+            (Some(p0), Some(p1)) => Some(p0.distance(&p1)),
+            _ => None
+        }
     }
 
-    fn add_track(&mut self, track: Track) {
+    pub fn add_track(&mut self, track: Track) {
         self.tracks.push(track);
     }
+
+    pub fn track(&self, name: &str) -> Option<&Track> {
+        self.tracks.iter().find(|track| track.name == name)
+    }
+}
+
+impl Position {
+    pub fn new(x: f64, y: f64) -> Position {
+        Position { x: x, y: y }
+    }
+
+    /// Reads the `PositionX`/`PositionY` columns. Both empty yields `None`.
+    pub fn parse(x: &ods::Cell, y: &ods::Cell) -> Result<Option<Position>, ParseError> {
+        match (x.as_float(), y.as_float()) {
+            (Some(x), Some(y)) => Ok(Some(Position::new(x, y))),
+            (None, _) if !x.is_empty() => Err(ParseError::new(3, format!("Invalid PositionX: {:?}", x))),
+            (_, None) if !y.is_empty() => Err(ParseError::new(4, format!("Invalid PositionY: {:?}", y))),
+            (None, None) => Ok(None),
+            (None, _) => Err(ParseError::new(3, "Missing PositionX")),
+            (_, None) => Err(ParseError::new(4, "Missing PositionY"))
+        }
+    }
+
+    pub fn distance(&self, other: &Position) -> f64 {
+        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
+    }
 }
 
 impl Display for Station {
@@ -290,40 +919,314 @@ impl Display for Station {
 }
 
 impl Track {
-    fn new<S: Into<String>>(station: S, name: S, owner: S) -> Track {
+    pub fn new<S: Into<String>>(station: S, name: S, kind: TrackKind, owner: S) -> Track {
+        let owner = owner.into();
+        let notes = owner.to_lowercase();
+
         Track {
             name: name.into(),
-            owner: owner.into(),
-            station: station.into()
+            platform: kind == TrackKind::Platform || notes.contains("bahnsteig") || notes.contains("bstg"),
+            through: notes.contains("durchgehend"),
+            owner: owner,
+            station: station.into(),
+            kind: kind,
+            length: None
         }
     }
+}
+
+impl TrackKind {
+    pub fn parse(subtype: &str) -> TrackKind {
+        match subtype.trim().to_lowercase().as_ref() {
+            "main" => TrackKind::Main,
+            "siding" => TrackKind::Siding,
+            "fiddleyard" | "fiddle yard" | "fiddle-yard" => TrackKind::FiddleYard,
+            "platform" => TrackKind::Platform,
+            _ => TrackKind::Other(String::from(subtype))
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match *self {
+            TrackKind::Main => "Main",
+            TrackKind::Siding => "Siding",
+            TrackKind::FiddleYard => "FiddleYard",
+            TrackKind::Platform => "Platform",
+            TrackKind::Other(ref name) => name
+        }
+    }
+}
+
+/// Reads the speed column of a traindef in km/h. Empty yields `None`.
+fn parse_speed(speed: &ods::Cell) -> Result<Option<usize>, ParseError> {
+    match speed.as_usize() {
+        Some(speed) => Ok(Some(speed)),
+        None if speed.is_empty() => Ok(None),
+        None => Err(ParseError::new(7, format!("Invalid speed: {:?}", speed)))
+    }
+}
 
-    fn parse(station: &str, name: &str, owner: &str) -> Track {
-        Track::new(station, name, owner)
+/// Reads the `PositionY/Length` column of a track. Empty yields `None`.
+fn parse_length(length: &ods::Cell) -> Result<Option<f64>, ParseError> {
+    match length.as_float() {
+        Some(length) => Ok(Some(length)),
+        None if length.is_empty() => Ok(None),
+        None => Err(ParseError::new(4, format!("Invalid length: {:?}", length)))
+    }
+}
+
+impl Display for TrackKind {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.name())
     }
 }
 
 impl Timetable {
-    fn new(train: usize, track: String, station: String, arrival: String,
-                 departure: String, remark: String) -> Timetable {
+    pub fn new(train: usize, track: String, station: String, arrival: Option<XplnTime>,
+                 departure: Option<XplnTime>, remark: String) -> Timetable {
         Timetable {
             train: train,
             track: track,
             station: station,
             arrival: arrival,
             departure: departure,
-            remark: remark
+            remark: remark,
+            sequence: None,
+            kind: StopKind::Stop,
+            row: 0
+        }
+    }
+
+    pub fn parse(row: &ods::Row, session_start: XplnTime) -> Result<Timetable, ParseError> {
+        let train = match row.cell(0).as_usize() {
+            Some(train) => train,
+            None => return Err(ParseError::new(1, format!("Invalid train number: {:?}", row.cell(0))))
+        };
+
+        let sequence = match row.cell(1).as_usize() {
+            Some(sequence) => Some(sequence),
+            None if row.cell(1).is_empty() => None,
+            None => return Err(ParseError::new(2, format!("Invalid enum: {:?}", row.cell(1))))
+        };
+
+        let arrival = match XplnTime::from_cell(&row.cell(4)) {
+            Ok(arrival) => arrival,
+            Err(err) => return Err(ParseError::new(5, format!("Invalid arrival: {}", err)))
+        };
+
+        let departure = match XplnTime::from_cell(&row.cell(5)) {
+            Ok(departure) => departure,
+            Err(err) => return Err(ParseError::new(6, format!("Invalid departure: {}", err)))
+        };
+
+        let mut timetable = Timetable::new(
+            train,
+            String::from(row.values.get(3).unwrap().as_ref()),
+            String::from(row.values.get(2).unwrap().as_ref()),
+            arrival.map(|time| time.relative_to(session_start)),
+            departure.map(|time| time.relative_to(session_start)),
+            row.values.get(10).cloned().unwrap_or(String::new()),
+        );
+
+        timetable.sequence = sequence;
+        timetable.row      = row.number;
+
+        Ok(timetable)
+    }
+
+    /// Time used to order entries, i.e. the arrival or, if the train does
+    /// not arrive, the departure.
+    pub fn time(&self) -> Option<XplnTime> {
+        self.arrival.or(self.departure)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::Cursor;
+
+    use document;
+    use network;
+    use ods;
+    use report::LoadReport;
+    use super::{parse_successor, regular_step, strings, Train, TrainName, Xpln, STATION_TRACK_TABLE, TRAINS_HEADER, TRAINS_TABLE};
+
+    #[test]
+    fn regular_step_is_first_step() {
+        assert_eq!(Some(10), regular_step(&[10, 20, 30, 40, 50]));
+        assert_eq!(Some(10), regular_step(&[10, 20, 30, 40, 45, 50, 60]));
+        assert_eq!(Some(10), regular_step(&[10, 10, 20, 30]));
+        assert_eq!(Some(1), regular_step(&[1, 2, 3, 5]));
+        assert_eq!(None, regular_step(&[10]));
+        assert_eq!(None, regular_step(&[10, 10]));
+    }
+
+    #[test]
+    fn regular_step_ignores_inserted_entries() {
+        // 25 was inserted later, 10 to 20 is no gap
+        let sequences = [10, 20, 25, 30];
+        let step = regular_step(&sequences).unwrap();
+
+        assert_eq!(10, step);
+        assert!(sequences.windows(2).all(|pair| pair[1] - pair[0] <= step));
+        assert_eq!(Some(10), regular_step(&[10, 20, 22, 24, 26, 30]));
+    }
+
+    #[test]
+    fn parse_successor_reads_link() {
+        assert_eq!(Some(Ok(62)), parse_successor("=> 62"));
+        assert_eq!(Some(Ok(62)), parse_successor("Lok eigenständig abstellen => 62"));
+        assert_eq!(Some(Ok(62)), parse_successor("=>62 Wagen bleiben stehen"));
+        assert_eq!(None, parse_successor("Lok eigenständig abstellen"));
+    }
+
+    #[test]
+    fn parse_successor_rejects_invalid_link() {
+        assert_eq!(Some(Err(String::from("Invalid successor link: => Lok"))), parse_successor("=> Lok"));
+        assert!(parse_successor("=>").unwrap().is_err());
+    }
+
+    #[test]
+    fn train_name_parse() {
+        let name = TrainName::parse("Dg 61 71", "", "");
+
+        assert_eq!("Dg 61 71", name.original);
+        assert_eq!("Dg", name.category);
+        assert_eq!(Some(String::from("61")), name.line);
+        assert_eq!(None, name.prefix);
+        assert_eq!(Some(String::from("71")), name.suffix);
+
+        let name = TrainName::parse("FD 61", "Ersatz", "Mo-Fr");
+
+        assert_eq!("Ersatz FD 61 Mo-Fr", name.original);
+        assert_eq!(Some(String::from("Ersatz")), name.prefix);
+        assert_eq!(Some(String::from("Mo-Fr")), name.suffix);
+    }
+
+    #[test]
+    fn train_name_sheet_name() {
+        assert_eq!("FD 61", TrainName::parse("FD 61", "Ersatz", "Mo-Fr").sheet_name());
+        assert_eq!("Dg 61", TrainName::parse("Dg 61 71", "", "").sheet_name());
+
+        let name = TrainName::parse("Dg 61 71", "", "");
+        let name = TrainName::parse(name.sheet_name(), "", name.suffix.as_ref().unwrap());
+
+        assert_eq!(TrainName::parse("Dg 61 71", "", ""), name);
+    }
+
+    #[test]
+    fn spreadsheet_round_trip() {
+        let document = ods::parse(File::open("untitled.ods").unwrap()).unwrap();
+
+        let mut xpln = Xpln::new();
+        assert!(!xpln.load(&document).unwrap().has_errors());
+
+        let data = ods::write(&xpln.to_spreadsheet(), Cursor::new(Vec::new())).unwrap();
+        let document = ods::parse(Cursor::new(data.into_inner())).unwrap();
+
+        let mut reloaded = Xpln::new();
+        assert!(!reloaded.load(&document).unwrap().has_errors());
+
+        assert_eq!(format!("{}", xpln), format!("{}", reloaded));
+
+        for train in xpln.sorted_trains() {
+            let other = reloaded.train(train.number).unwrap();
+            let cells = |train: &Train| train.objects.iter().map(|row| row.cells.clone()).collect::<Vec<_>>();
+
+            assert!(!train.object.is_empty() && !train.objects.is_empty());
+            assert_eq!(train.object, other.object);
+            assert_eq!(cells(train), cells(other));
+        }
+
+        let (original, reloaded) = (xpln.to_spreadsheet(), reloaded.to_spreadsheet());
+        assert_eq!(original.tables().len(), reloaded.tables().len());
+
+        for (table, reloaded) in original.tables().iter().zip(reloaded.tables().iter()) {
+            assert_eq!(format!("{}", table), format!("{}", reloaded));
         }
     }
 
-    fn parse(values: &Vec<String>) -> Result<Timetable, ParseIntError> {
-        Ok(Timetable::new(
-            try!(usize::from_str(values.get(0).unwrap())),
-            String::from(values.get(3).unwrap().as_ref()),
-            String::from(values.get(2).unwrap().as_ref()),
-            String::from(values.get(4).unwrap().as_ref()),
-            String::from(values.get(5).unwrap().as_ref()),
-            String::from(values.get(10).unwrap().as_ref()),
-        ))
+    /// Diagnostics with all their fields as text, which can be compared.
+    fn diagnostics(report: &LoadReport) -> Vec<String> {
+        report.diagnostics.iter().map(|diagnostic| format!("{:?} {} {:?} {:?} {} {}",
+            diagnostic.severity, diagnostic.sheet, diagnostic.row, diagnostic.column,
+            diagnostic.code, diagnostic.message
+        )).collect()
+    }
+
+    #[test]
+    fn document_loads_like_spreadsheet() {
+        let text = concat!(
+            "lines:\n",
+            "  - {id: 1, from: A, to: B}\n",
+            "stations:\n",
+            "  - name: A\n",
+            "    remark: Alpha\n",
+            "    tracks:\n",
+            "      - {name: 1, kind: Main, length: 120}\n",
+            "  - name: B\n",
+            "    position: {x: 10, y: 20}\n",
+            "    tracks:\n",
+            "      - {name: 2, kind: FiddleYard}\n",
+            "trains:\n",
+            "  - number: 12\n",
+            "    designation: {original: \"x Dg 12 71\", prefix: x}\n",
+            "    speed: 80\n",
+            "    wheels: 24\n",
+            "    timetable:\n",
+            "      - {sequence: 10, station: A, track: 1, departure: \"5:10\"}\n",
+            "      - {sequence: 20, station: B, track: 2, arrival: \"5:30\"}\n",
+            "  - number: abc\n",
+            "    name: P 13\n"
+        );
+
+        let string = |value: &str| ods::Cell::String(String::from(value));
+        let time   = |hours: i64, minutes: i64| ods::Cell::Time(hours * 3600 + minutes * 60);
+        let empty  = || ods::Cell::Empty;
+
+        let mut stations = ods::Table::new(STATION_TRACK_TABLE);
+        stations.push(strings(&network::STATION_TRACK_HEADER));
+        stations.push(vec![string("A"), empty(), empty(), empty(), string("Alpha"), string("Station"),
+            empty(), empty(), string("/00001Strecke: A-B")]);
+        stations.push(vec![string("A"), empty(), ods::Cell::Float(1.0), ods::Cell::Float(120.0), empty(),
+            string("Track"), string("Main")]);
+        stations.push(vec![string("B"), empty(), ods::Cell::Float(10.0), ods::Cell::Float(20.0), empty(),
+            string("Station")]);
+        stations.push(vec![string("B"), empty(), ods::Cell::Float(2.0), empty(), empty(),
+            string("Track"), string("FiddleYard")]);
+
+        let mut trains = ods::Table::new(TRAINS_TABLE);
+        trains.push(strings(&TRAINS_HEADER));
+        trains.push(vec![ods::Cell::Float(12.0), empty(), string("x"), empty(), empty(), empty(),
+            ods::Cell::Float(80.0), empty(), string("traindef"), string("Dg 12 71")]);
+        trains.push(vec![ods::Cell::Float(12.0), empty(), empty(), empty(), empty(), empty(),
+            ods::Cell::Float(24.0), empty(), string("wheel")]);
+        trains.push(vec![ods::Cell::Float(12.0), ods::Cell::Float(10.0), string("A"), ods::Cell::Float(1.0),
+            empty(), time(5, 10), empty(), empty(), string("timetable"), string("Dg 12")]);
+        trains.push(vec![ods::Cell::Float(12.0), ods::Cell::Float(20.0), string("B"), ods::Cell::Float(2.0),
+            time(5, 30), empty(), empty(), empty(), string("timetable"), string("Dg 12")]);
+        trains.push(vec![string("abc"), empty(), empty(), empty(), empty(), empty(),
+            empty(), empty(), string("traindef"), string("P 13")]);
+
+        let mut spreadsheet = ods::Spreadsheet::new();
+        spreadsheet.push(stations);
+        spreadsheet.push(trains);
+
+        let mut xpln = Xpln::new();
+        let report = xpln.load(&spreadsheet).unwrap();
+
+        let mut loaded = Xpln::new();
+        let loaded_report = loaded.load(&document::parse_str(text).unwrap()).unwrap();
+
+        assert_eq!(format!("{}", xpln), format!("{}", loaded));
+
+        for (table, loaded) in xpln.to_spreadsheet().tables().iter().zip(loaded.to_spreadsheet().tables().iter()) {
+            assert_eq!(format!("{}", table), format!("{}", loaded));
+        }
+
+        assert_eq!(1, report.errors().len());
+        assert_eq!("invalid-traindef", report.errors()[0].code);
+        assert_eq!(diagnostics(&report), diagnostics(&loaded_report));
     }
 }